@@ -1,5 +1,5 @@
 extern crate protobuf_iter;
-use protobuf_iter::{Packed, PackedIter, PackedVarint};
+use protobuf_iter::{BulkDecode, Packed, PackedIter, PackedVarint};
 
 fn parse_varint(data: &[u8]) {
     let _ = PackedVarint::parse(data).unwrap();
@@ -30,7 +30,7 @@ fn criterion_benchmark(c: &mut Criterion) {
             bytes += 1;
         }
     }
-    c.bench_function("parse_varint iter", move |b| {
+    c.bench_function("parse_varint iter", |b| {
         b.iter(|| {
             let mut iter: PackedIter<'_, PackedVarint, u32> = PackedIter::new(&buf);
             for i in 0..131073u32 {
@@ -39,6 +39,48 @@ fn criterion_benchmark(c: &mut Criterion) {
             assert_eq!(None, iter.next());
         })
     });
+
+    c.bench_function("decode_into", move |b| {
+        let mut out = Vec::with_capacity(131073);
+        b.iter(|| {
+            out.clear();
+            let mut iter: PackedIter<'_, PackedVarint, u32> = PackedIter::new(&buf);
+            iter.decode_into(&mut out);
+            assert_eq!(131073, out.len());
+        })
+    });
+
+    let mut buf = Vec::<u8>::new();
+    for i in 0..131073u64 {
+        let mut n = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (i % 64);
+        loop {
+            let b = n as u8 & 0b0111_1111;
+            n >>= 7;
+            if n == 0 {
+                buf.push(b);
+                break;
+            }
+            buf.push(0b1000_0000 | b);
+        }
+    }
+    c.bench_function("parse_varint iter mixed lengths", |b| {
+        let mut out = Vec::with_capacity(131073);
+        b.iter(|| {
+            out.clear();
+            let iter: PackedIter<'_, PackedVarint, u64> = PackedIter::new(&buf);
+            out.extend(iter);
+            assert_eq!(131073, out.len());
+        })
+    });
+    c.bench_function("decode_into mixed lengths", move |b| {
+        let mut out = Vec::with_capacity(131073);
+        b.iter(|| {
+            out.clear();
+            let mut iter: PackedIter<'_, PackedVarint, u64> = PackedIter::new(&buf);
+            iter.decode_into(&mut out);
+            assert_eq!(131073, out.len());
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
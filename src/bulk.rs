@@ -0,0 +1,157 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::packed::*;
+use crate::simd::*;
+use crate::value32::*;
+use crate::value64::*;
+use crate::varint::*;
+
+/// Decoding of many packed elements at once
+///
+/// Like iteration, decoding stops at the first element that does not
+/// parse. Elements that were not decoded remain in the iterator.
+pub trait BulkDecode<T> {
+    /// Decode up to `out.len()` elements, returning how many were written
+    fn decode_to_slice(&mut self, out: &mut [T]) -> usize;
+
+    /// Append all remaining elements to `out`
+    #[cfg(feature = "alloc")]
+    fn decode_into(&mut self, out: &mut Vec<T>);
+}
+
+/// Elements decoded at once by decode_into()
+#[cfg(feature = "alloc")]
+const VARINT_CHUNK: usize = 1024;
+
+impl<'a, T: From<Varint> + Default + Clone> BulkDecode<T> for PackedIter<'a, PackedVarint, T> {
+    fn decode_to_slice(&mut self, out: &mut [T]) -> usize {
+        let (count, consumed) = decode_varints(self.data, out, |value| T::from(Varint { value }));
+        self.data = &self.data[consumed..];
+        count
+    }
+
+    #[cfg(feature = "alloc")]
+    fn decode_into(&mut self, out: &mut Vec<T>) {
+        // In chunks, as there are at most as many elements as bytes
+        loop {
+            let start = out.len();
+            let len = self.data.len().min(VARINT_CHUNK);
+            out.resize(start + len, T::default());
+            let count = self.decode_to_slice(&mut out[start..]);
+            out.truncate(start + count);
+            if count < len || len == 0 {
+                break;
+            }
+        }
+    }
+}
+
+macro_rules! bulk_fixed {
+    ($packed: ty, $value: ident, $width: expr, $($target: ty),*) => {
+        $(
+            impl<'a> BulkDecode<$target> for PackedIter<'a, $packed, $target> {
+                fn decode_to_slice(&mut self, out: &mut [$target]) -> usize {
+                    let count = out.len().min(self.data.len() / $width);
+                    let (bytes, rest) = self.data.split_at(count * $width);
+                    if cfg!(target_endian = "little") && size_of::<$target>() == $width {
+                        // SAFETY: the target is a primitive number of the
+                        // same width, so any bytes are a valid value in the
+                        // wire's byte order.
+                        unsafe {
+                            core::ptr::copy_nonoverlapping(
+                                bytes.as_ptr(),
+                                out.as_mut_ptr().cast::<u8>(),
+                                bytes.len(),
+                            );
+                        }
+                    } else {
+                        for (value, data) in out.iter_mut().zip(bytes.chunks_exact($width)) {
+                            *value = $value { data }.into();
+                        }
+                    }
+                    self.data = rest;
+                    count
+                }
+
+                #[cfg(feature = "alloc")]
+                fn decode_into(&mut self, out: &mut Vec<$target>) {
+                    let start = out.len();
+                    out.resize(start + self.data.len() / $width, Default::default());
+                    self.decode_to_slice(&mut out[start..]);
+                }
+            }
+        )*
+    };
+}
+
+bulk_fixed!(PackedValue32, Value32, 4, u32, i32, f32, u64, i64);
+bulk_fixed!(PackedValue64, Value64, 8, u32, i32, u64, i64, f64);
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::encode::*;
+
+    #[test]
+    fn varints() {
+        let values: Vec<u64> = (0..1000).map(|i| (i * i * i) << (i % 40)).collect();
+        let mut data = vec![];
+        for value in &values {
+            encode_varint(*value, &mut data);
+        }
+        let mut decoded = vec![7];
+        PackedIter::<PackedVarint, u64>::new(&data).decode_into(&mut decoded);
+        assert_eq!(7, decoded[0]);
+        assert_eq!(values, decoded[1..]);
+
+        // Zigzag through From<Varint>
+        let mut decoded = vec![];
+        PackedIter::<PackedVarint, i64>::new(&data).decode_into(&mut decoded);
+        assert_eq!(
+            PackedIter::<PackedVarint, i64>::new(&data).collect::<Vec<_>>(),
+            decoded
+        );
+
+        let mut iter = PackedIter::<PackedVarint, u64>::new(&data);
+        let mut out = [0; 600];
+        assert_eq!(600, iter.decode_to_slice(&mut out));
+        assert_eq!(values[..600], out);
+        assert_eq!(400, iter.decode_to_slice(&mut out));
+        assert_eq!(values[600..], out[..400]);
+        assert_eq!(None, iter.next());
+    }
+
+    #[test]
+    fn malformed_varints() {
+        // 1, 300, then unterminated
+        let mut data = vec![0x01, 0xac, 0x02];
+        data.extend([0x80; 20]);
+        let mut iter = PackedIter::<PackedVarint, u32>::new(&data);
+        let mut decoded = vec![];
+        iter.decode_into(&mut decoded);
+        assert_eq!(vec![1, 300], decoded);
+        assert_eq!(20, iter.data.len());
+    }
+
+    #[test]
+    fn fixed() {
+        let data = [1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 3, 0, 0, 0, 4, 0, 0];
+        let mut decoded = vec![];
+        PackedIter::<PackedValue32, i32>::new(&data).decode_into(&mut decoded);
+        assert_eq!(vec![1, -1, 3], decoded);
+        let mut decoded = vec![];
+        PackedIter::<PackedValue32, u64>::new(&data).decode_into(&mut decoded);
+        assert_eq!(vec![1, 0xffff_ffff, 3], decoded);
+
+        let mut decoded = vec![];
+        PackedIter::<PackedValue32, f32>::new(&1.5f32.to_le_bytes()).decode_into(&mut decoded);
+        assert_eq!(vec![1.5], decoded);
+
+        let mut iter = PackedIter::<PackedValue64, u64>::new(&data);
+        let mut out = [0; 4];
+        assert_eq!(1, iter.decode_to_slice(&mut out));
+        assert_eq!(0xffff_ffff_0000_0001, out[0]);
+        assert_eq!(7, iter.data.len());
+    }
+}
@@ -0,0 +1,147 @@
+use bytes::Bytes;
+
+use crate::field::*;
+use crate::parse::*;
+use crate::value32::*;
+use crate::value64::*;
+use crate::varint::*;
+
+/// Owned counterpart of ParseValue that shares its buffer
+///
+/// Length-delimited values are reference-counted slices of the parent,
+/// so they outlive iteration and can be sent to other threads.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum BytesValue {
+    Value32([u8; 4]),
+    Value64([u8; 8]),
+    Varint(Varint),
+    LengthDelimited(Bytes),
+}
+
+impl BytesValue {
+    pub fn wire_type(&self) -> WireType {
+        self.as_borrowed().wire_type()
+    }
+
+    /// View as ParseValue for its From conversions
+    pub fn as_borrowed(&self) -> ParseValue<'_> {
+        match self {
+            BytesValue::Value32(data) => ParseValue::Value32(Value32 { data }),
+            BytesValue::Value64(data) => ParseValue::Value64(Value64 { data }),
+            BytesValue::Varint(varint) => ParseValue::Varint(*varint),
+            BytesValue::LengthDelimited(data) => ParseValue::LengthDelimited(data),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct BytesField {
+    pub tag: u32,
+    pub value: BytesValue,
+}
+
+impl BytesField {
+    pub fn as_borrowed(&self) -> Field<'_> {
+        Field {
+            tag: self.tag,
+            value: self.value.as_borrowed(),
+        }
+    }
+}
+
+/// MessageIter over a Bytes buffer, yielding BytesField
+#[derive(Debug, Clone, Default)]
+pub struct BytesMessageIter {
+    data: Bytes,
+}
+
+impl BytesMessageIter {
+    pub fn new(data: Bytes) -> Self {
+        BytesMessageIter { data }
+    }
+
+    /// The data that has not been iterated yet
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.data
+    }
+}
+
+impl From<Bytes> for BytesMessageIter {
+    fn from(data: Bytes) -> Self {
+        Self::new(data)
+    }
+}
+
+/// Recursive for LengthDelimited
+impl From<BytesValue> for BytesMessageIter {
+    fn from(value: BytesValue) -> Self {
+        match value {
+            BytesValue::LengthDelimited(data) => Self::new(data),
+            _ => panic!("Expected buffer to parse"),
+        }
+    }
+}
+
+impl Iterator for BytesMessageIter {
+    type Item = BytesField;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (field, rest) = parse_field(&self.data).ok()?;
+        let tag = field.tag;
+        let consumed = self.data.len() - rest.len();
+        let value = match field.value {
+            ParseValue::Value32(value32) => BytesValue::Value32(value32.data.try_into().unwrap()),
+            ParseValue::Value64(value64) => BytesValue::Value64(value64.data.try_into().unwrap()),
+            ParseValue::Varint(varint) => BytesValue::Varint(varint),
+            ParseValue::LengthDelimited(data) => {
+                BytesValue::LengthDelimited(self.data.slice_ref(data))
+            }
+        };
+        self.data = self.data.slice(consumed..);
+        Some(BytesField { tag, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_iter::*;
+
+    // 1: 150, 2: { 1: 1 }, 3: fixed32 7, 4: fixed64 8
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x02, 0x08, 0x01, 0x1d, 7, 0, 0, 0, 0x21, 8, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn same_as_message_iter() {
+        let fields: Vec<BytesField> = BytesMessageIter::new(Bytes::from_static(DATA)).collect();
+        assert_eq!(
+            MessageIter::new(DATA).collect::<Vec<_>>(),
+            fields
+                .iter()
+                .map(BytesField::as_borrowed)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(7, u32::from(fields[2].value.as_borrowed()));
+        assert_eq!(WireType::Value64, fields[3].value.wire_type());
+    }
+
+    #[test]
+    fn shared_across_threads() {
+        let buffer = Bytes::copy_from_slice(DATA);
+        let nested = BytesMessageIter::new(buffer.clone())
+            .find(|field| field.tag == 2)
+            .unwrap()
+            .value;
+        let handle = std::thread::spawn(move || {
+            if let BytesValue::LengthDelimited(data) = &nested {
+                // A slice of the parent, not a copy
+                assert_eq!(buffer.as_ptr().wrapping_add(5), data.as_ptr());
+            }
+            BytesMessageIter::from(nested)
+                .map(|field| u32::from(field.value.as_borrowed()))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(vec![1], handle.join().unwrap());
+    }
+}
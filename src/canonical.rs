@@ -0,0 +1,267 @@
+use alloc::{vec, vec::Vec};
+
+use crate::encode::*;
+use crate::field::*;
+use crate::message_iter::for_each_field;
+use crate::packed::*;
+use crate::parse::*;
+use crate::schema::*;
+use crate::walk::DEFAULT_MAX_DEPTH;
+
+/// Deterministic re-serialization without a schema
+///
+/// Fields are stably sorted by tag, and keys, varints and length
+/// prefixes are re-encoded in their shortest form. Length-delimited
+/// values are copied as they are because they may not be messages.
+/// Fails if `data` does not parse completely, so that different
+/// inputs never have the same canonical form.
+pub fn canonicalize(data: &[u8]) -> ParseResult<Vec<u8>> {
+    let mut fields = parse_fields(data)?;
+    fields.sort_by_key(|field| field.tag);
+    let mut out = Vec::with_capacity(data.len());
+    for field in fields {
+        field.encode(&mut out);
+    }
+    Ok(out)
+}
+
+fn parse_fields(data: &[u8]) -> ParseResult<Vec<Field<'_>>> {
+    let mut fields = vec![];
+    for_each_field(data, |field| fields.push(field))?;
+    Ok(fields)
+}
+
+/// Deterministic re-serialization according to a message type
+///
+/// In addition to canonicalize(), nested messages are canonicalized
+/// recursively, occurrences of singular messages are merged into one,
+/// only the last occurrence of other singular fields is kept and
+/// repeated scalars are packed. Fields with tags or wire types that
+/// the schema does not know are kept. Nested messages and packed
+/// values must parse completely, and nesting is limited to
+/// DEFAULT_MAX_DEPTH levels.
+pub fn canonicalize_with_schema(
+    schema: &Schema,
+    message: &MessageDescriptor,
+    data: &[u8],
+) -> ParseResult<Vec<u8>> {
+    canonicalize_nested(schema, message, data, 0)
+}
+
+fn canonicalize_nested(
+    schema: &Schema,
+    message: &MessageDescriptor,
+    data: &[u8],
+    depth: usize,
+) -> ParseResult<Vec<u8>> {
+    if depth > DEFAULT_MAX_DEPTH {
+        return Err(ParseError::DepthLimitExceeded);
+    }
+    let mut fields = parse_fields(data)?;
+    fields.sort_by_key(|field| field.tag);
+    let mut out = Vec::with_capacity(data.len());
+    for group in fields.chunk_by(|a, b| a.tag == b.tag) {
+        let tag = group[0].tag;
+        let descriptor = match message.by_tag(tag) {
+            Some(descriptor) => descriptor,
+            None => {
+                group.iter().for_each(|field| field.encode(&mut out));
+                continue;
+            }
+        };
+        let wire_type = descriptor.field_type.wire_type();
+        let (matching, mismatching): (Vec<&Field>, Vec<&Field>) = group.iter().partition(|field| {
+            let actual = field.value.wire_type();
+            actual == wire_type
+                || (descriptor.repeated
+                    && descriptor.field_type.is_packable()
+                    && actual == WireType::LengthDelimited)
+        });
+        mismatching.iter().for_each(|field| field.encode(&mut out));
+        if matching.is_empty() {
+            continue;
+        }
+
+        let nested = descriptor
+            .message_type()
+            .and_then(|name| schema.message(name));
+        if descriptor.repeated && descriptor.field_type.is_packable() {
+            let mut packed = vec![];
+            for field in matching {
+                pack_into(&field.value, wire_type, &mut packed)?;
+            }
+            // Like a missing field
+            if !packed.is_empty() {
+                encode_length_delimited(tag, &packed, &mut out);
+            }
+        } else if let Some(nested) = nested {
+            if descriptor.repeated {
+                for field in matching {
+                    let payload = canonicalize_nested(schema, nested, payload(field), depth + 1)?;
+                    encode_length_delimited(tag, &payload, &mut out);
+                }
+            } else {
+                let mut merged = vec![];
+                for field in matching {
+                    merged.extend_from_slice(payload(field));
+                }
+                let payload = canonicalize_nested(schema, nested, &merged, depth + 1)?;
+                encode_length_delimited(tag, &payload, &mut out);
+            }
+        } else if descriptor.repeated {
+            matching.iter().for_each(|field| field.encode(&mut out));
+        } else if let Some(field) = matching.last() {
+            field.encode(&mut out);
+        }
+    }
+    Ok(out)
+}
+
+fn payload<'a>(field: &Field<'a>) -> &'a [u8] {
+    field.value.clone().get_data()
+}
+
+/// Append the elements of a packed or unpacked occurrence
+///
+/// Fails if a packed payload ends in a partial element.
+fn pack_into(value: &ParseValue, wire_type: WireType, packed: &mut Vec<u8>) -> ParseResult<()> {
+    match value {
+        ParseValue::LengthDelimited(data) => match wire_type {
+            WireType::Varint => {
+                let mut iter = PackedIter::<PackedVarint, u64>::new(data);
+                for varint in iter.by_ref() {
+                    encode_varint(varint, packed);
+                }
+                if !iter.data.is_empty() {
+                    return Err(ParseError::NotEnoughData);
+                }
+            }
+            _ => {
+                let width = if wire_type == WireType::Value32 { 4 } else { 8 };
+                if !data.len().is_multiple_of(width) {
+                    return Err(ParseError::NotEnoughData);
+                }
+                packed.extend_from_slice(data);
+            }
+        },
+        _ => value.encode(packed),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new().message_type(
+            MessageDescriptor::new("Msg")
+                .field(FieldDescriptor::new("id", 1, FieldType::Uint32))
+                .field(FieldDescriptor::repeated("values", 2, FieldType::Int64))
+                .field(FieldDescriptor::new(
+                    "sub",
+                    3,
+                    FieldType::Message("Msg".to_owned()),
+                ))
+                .field(FieldDescriptor::repeated("names", 4, FieldType::String))
+                .field(FieldDescriptor::repeated("ratios", 5, FieldType::Float)),
+        )
+    }
+
+    #[test]
+    fn without_schema() {
+        // 2: 1 (non-minimal), 1: "a" (non-minimal length), 2: 2
+        let data = [0x10, 0x81, 0x00, 0x0a, 0x81, 0x00, b'a', 0x10, 0x02];
+        assert_eq!(
+            vec![0x0a, 0x01, b'a', 0x10, 0x01, 0x10, 0x02],
+            canonicalize(&data).unwrap()
+        );
+        let canonical = canonicalize(&data).unwrap();
+        assert_eq!(canonical, canonicalize(&canonical).unwrap());
+        // Trailing bytes that do not parse
+        let mut truncated = data.to_vec();
+        truncated.extend([0x12, 0x05, b'x']);
+        assert_eq!(Err(ParseError::NotEnoughData), canonicalize(&truncated));
+    }
+
+    #[test]
+    fn with_schema() {
+        let schema = schema();
+        let message = schema.message("Msg").unwrap();
+        // sub: { values: [1], id: 1 }, id: 5, values: [2, 3] (packed),
+        // sub: { id: 2 }, id: 6, values: 4, names: "b", names: "a",
+        // ratios: 1.0, 99: 7
+        let data = [
+            0x1a, 0x04, 0x10, 0x01, 0x08, 0x01, 0x08, 0x05, 0x12, 0x02, 0x02, 0x03, 0x1a, 0x02,
+            0x08, 0x02, 0x08, 0x06, 0x10, 0x04, 0x22, 0x01, b'b', 0x22, 0x01, b'a', 0x2d, 0x00,
+            0x00, 0x80, 0x3f, 0x98, 0x06, 0x07,
+        ];
+        assert_eq!(
+            vec![
+                0x08, 0x06, 0x12, 0x03, 0x02, 0x03, 0x04, 0x1a, 0x05, 0x08, 0x02, 0x12, 0x01, 0x01,
+                0x22, 0x01, b'b', 0x22, 0x01, b'a', 0x2a, 0x04, 0x00, 0x00, 0x80, 0x3f, 0x98, 0x06,
+                0x07,
+            ],
+            canonicalize_with_schema(&schema, message, &data).unwrap()
+        );
+        // values: [] (packed)
+        assert!(
+            canonicalize_with_schema(&schema, message, &[0x12, 0x00])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn wire_type_mismatch() {
+        let schema = schema();
+        let message = schema.message("Msg").unwrap();
+        // id as fixed32, then as varint
+        let data = [0x0d, 1, 0, 0, 0, 0x08, 0x02];
+        assert_eq!(
+            vec![0x0d, 1, 0, 0, 0, 0x08, 0x02],
+            canonicalize_with_schema(&schema, message, &data).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let schema = schema();
+        let message = schema.message("Msg").unwrap();
+        // sub: { id: truncated }
+        let data = [0x1a, 0x01, 0x08];
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            canonicalize_with_schema(&schema, message, &data)
+        );
+        // ratios with a partial element
+        let data = [0x2a, 0x03, 0x00, 0x00, 0x80];
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            canonicalize_with_schema(&schema, message, &data)
+        );
+        // values with an unterminated varint
+        let data = [0x12, 0x02, 0x01, 0x80];
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            canonicalize_with_schema(&schema, message, &data)
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let schema = schema();
+        let message = schema.message("Msg").unwrap();
+        // sub: { sub: { ... id: 1 } }
+        let nested = |depth| nested_message(3, depth, &[0x08, 0x01]);
+        let data = nested(DEFAULT_MAX_DEPTH);
+        assert_eq!(
+            Ok(data.clone()),
+            canonicalize_with_schema(&schema, message, &data)
+        );
+        assert_eq!(
+            Err(ParseError::DepthLimitExceeded),
+            canonicalize_with_schema(&schema, message, &nested(DEFAULT_MAX_DEPTH + 1))
+        );
+    }
+}
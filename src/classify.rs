@@ -0,0 +1,236 @@
+use alloc::vec::Vec;
+
+use crate::message_iter::*;
+use crate::validate::{MAX_TAG, RESERVED_TAGS};
+
+/// What a length-delimited payload probably contains
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum PayloadKind {
+    Message,
+    String,
+    PackedVarint,
+    PackedFixed32,
+    PackedFixed64,
+    /// None of the above is likely
+    Bytes,
+}
+
+/// Confidence between 0.0 and 1.0 for each interpretation
+///
+/// Returned by classify(). The scores are independent of each other,
+/// as a payload can be valid in several ways at once.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Guess {
+    pub message: f32,
+    pub string: f32,
+    pub packed_varint: f32,
+    pub packed_fixed32: f32,
+    pub packed_fixed64: f32,
+}
+
+impl Guess {
+    /// The most confident interpretation, or Bytes below 0.5
+    ///
+    /// Ties are resolved in declaration order of PayloadKind.
+    pub fn best(&self) -> PayloadKind {
+        let candidates = [
+            (PayloadKind::Message, self.message),
+            (PayloadKind::String, self.string),
+            (PayloadKind::PackedVarint, self.packed_varint),
+            (PayloadKind::PackedFixed32, self.packed_fixed32),
+            (PayloadKind::PackedFixed64, self.packed_fixed64),
+        ];
+        let mut best = (PayloadKind::Bytes, 0.5);
+        for (kind, confidence) in candidates {
+            if confidence >= best.1 && (best.0 == PayloadKind::Bytes || confidence > best.1) {
+                best = (kind, confidence);
+            }
+        }
+        best.0
+    }
+}
+
+/// Score the interpretations of a length-delimited payload
+///
+/// Empty payloads are equally valid as anything, so all scores are 0.
+pub fn classify(data: &[u8]) -> Guess {
+    if data.is_empty() {
+        return Guess::default();
+    }
+    Guess {
+        message: message_score(data),
+        string: string_score(data),
+        packed_varint: packed_varint_score(data),
+        packed_fixed32: fixed_score(data, 4),
+        packed_fixed64: fixed_score(data, 8),
+    }
+}
+
+fn message_score(data: &[u8]) -> f32 {
+    if !is_message(data) {
+        return 0.0;
+    }
+    let tags: Vec<u32> = MessageIter::new(data).map(|field| field.tag).collect();
+    if tags
+        .iter()
+        .any(|tag| *tag < 1 || *tag > MAX_TAG || RESERVED_TAGS.contains(tag))
+    {
+        return 0.0;
+    }
+    let mut score = 0.5;
+    // Schemas rarely use large field numbers
+    if tags.iter().all(|tag| *tag <= 1000) {
+        score += 0.2;
+    }
+    // Serializers emit fields in tag order
+    if tags.windows(2).all(|pair| pair[0] <= pair[1]) {
+        score += 0.2;
+    }
+    if tags.len() > 1 {
+        score += 0.1;
+    }
+    score
+}
+
+fn string_score(data: &[u8]) -> f32 {
+    let text = match core::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return 0.0,
+    };
+    let total = text.chars().count();
+    let printable = text
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .count();
+    if printable == total {
+        1.0
+    } else {
+        0.8 * printable as f32 / total as f32
+    }
+}
+
+fn packed_varint_score(data: &[u8]) -> f32 {
+    let mut count = 0;
+    let mut len = 0;
+    let mut minimal = true;
+    for byte in data {
+        len += 1;
+        if byte & 0x80 != 0 {
+            // The 10th byte must terminate a varint
+            if len >= 10 {
+                return 0.0;
+            }
+            continue;
+        }
+        // A final zero after continuation bytes is padding
+        minimal &= len == 1 || *byte != 0;
+        count += 1;
+        len = 0;
+    }
+    if len > 0 {
+        return 0.0;
+    }
+    let mut score = 0.3;
+    if minimal {
+        score += 0.2;
+    }
+    if count > 1 {
+        score += 0.1;
+    }
+    score
+}
+
+fn fixed_score(data: &[u8], width: usize) -> f32 {
+    if !data.len().is_multiple_of(width) {
+        return 0.0;
+    }
+    let plausible = data.chunks_exact(width).all(|chunk| {
+        let (small_int, float) = if width == 4 {
+            let bits = u32::from_le_bytes(chunk.try_into().unwrap());
+            (bits < 1 << 24, plausible_float(f32::from_bits(bits) as f64))
+        } else {
+            let bits = u64::from_le_bytes(chunk.try_into().unwrap());
+            (bits < 1 << 48, plausible_float(f64::from_bits(bits)))
+        };
+        small_int || float
+    });
+    // Much less likely by chance than valid varints
+    if plausible { 0.7 } else { 0.3 }
+}
+
+/// Zero or a normal number of moderate magnitude
+fn plausible_float(value: f64) -> bool {
+    value == 0.0 || (value.is_normal() && (1e-12..1e12).contains(&value.abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message() {
+        // 1: 150, 2: "testing"
+        let data = [
+            0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g',
+        ];
+        let guess = classify(&data);
+        assert_eq!(1.0, guess.message);
+        assert_eq!(0.6, guess.packed_varint);
+        assert_eq!(PayloadKind::Message, guess.best());
+        assert_eq!(0.9, classify(&[0x10, 0x01]).message);
+        // Out of order
+        assert_eq!(0.8, classify(&[0x10, 0x01, 0x08, 0x01]).message);
+        // Reserved field number
+        assert_eq!(0.0, classify(&[0xc0, 0xa3, 0x09, 0x01]).message);
+    }
+
+    #[test]
+    fn string() {
+        let guess = classify("Grüße\n".as_bytes());
+        assert_eq!(1.0, guess.string);
+        assert_eq!(PayloadKind::String, guess.best());
+        assert_eq!(0.4, classify(b"a\x01").string);
+        assert_eq!(0.0, classify(&[0xff, 0xfe]).string);
+    }
+
+    #[test]
+    fn packed_varints() {
+        let guess = classify(&[0x03, 0x8e, 0x02, 0x9e, 0xa7, 0x05]);
+        assert_eq!(0.6, guess.packed_varint);
+        assert_eq!(0.0, guess.message);
+        assert_eq!(PayloadKind::PackedVarint, guess.best());
+        // Padded encoding
+        assert_eq!(0.3, classify(&[0x81, 0x00]).packed_varint);
+        // Unterminated
+        assert_eq!(0.0, classify(&[0x01, 0x81]).packed_varint);
+        assert_eq!(0.0, classify(&[0xff; 11]).packed_varint);
+    }
+
+    #[test]
+    fn packed_fixed() {
+        let mut floats = vec![];
+        for value in [1.5f32, -2.25, 1000.0] {
+            floats.extend_from_slice(&value.to_le_bytes());
+        }
+        let guess = classify(&floats);
+        assert_eq!(0.7, guess.packed_fixed32);
+        assert_eq!(0.0, guess.packed_fixed64);
+        assert_eq!(PayloadKind::PackedFixed32, guess.best());
+
+        let mut doubles = vec![];
+        for value in [std::f64::consts::PI, std::f64::consts::E] {
+            doubles.extend_from_slice(&value.to_le_bytes());
+        }
+        let guess = classify(&doubles);
+        assert_eq!(0.3, guess.packed_fixed32);
+        assert_eq!(0.7, guess.packed_fixed64);
+        assert_eq!(PayloadKind::PackedFixed64, guess.best());
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(Guess::default(), classify(&[]));
+        assert_eq!(PayloadKind::Bytes, classify(&[]).best());
+        assert_eq!(PayloadKind::Bytes, classify(&[0xff, 0xff, 0xff]).best());
+    }
+}
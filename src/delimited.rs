@@ -0,0 +1,56 @@
+use crate::message_iter::*;
+use crate::parse::*;
+
+/// Messages in a stream where each is prefixed with its length
+///
+/// This is the format written by `writeDelimitedTo()` in the reference
+/// implementation. Like MessageIter, iteration stops at the first
+/// prefix that does not parse or exceeds the data, which as_slice()
+/// then returns.
+#[derive(Clone, Default)]
+pub struct DelimitedIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DelimitedIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        DelimitedIter { data }
+    }
+
+    /// The data that has not been iterated yet
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for DelimitedIter<'a> {
+    type Item = MessageIter<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (len, rest) = parse_varint(self.data).ok()?;
+        let len = usize::try_from(len.value).ok()?;
+        let message = rest.get(..len)?;
+        self.data = &rest[len..];
+        Some(MessageIter::new(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream() {
+        // { 1: 1 }, {}, { 2: "a" }, truncated
+        let data = [0x02, 0x08, 0x01, 0x00, 0x03, 0x12, 0x01, b'a', 0x05, 0x08];
+        let mut iter = DelimitedIter::new(&data);
+        assert_eq!(
+            vec![1],
+            iter.next().unwrap().tag::<u32>(1).collect::<Vec<_>>()
+        );
+        assert_eq!(0, iter.next().unwrap().count());
+        assert_eq!(1, iter.next().unwrap().count());
+        assert!(iter.next().is_none());
+        assert_eq!(&[0x05, 0x08], iter.as_slice());
+    }
+}
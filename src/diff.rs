@@ -0,0 +1,336 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{vec, vec::Vec};
+
+use crate::message_iter::for_each_field;
+use crate::parse::*;
+use crate::walk::DEFAULT_MAX_DEPTH;
+
+/// One difference found by diff()
+///
+/// Paths are the tags leading to the field, starting at the top-level
+/// message.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Difference<'a> {
+    Added {
+        path: Vec<u32>,
+        value: ParseValue<'a>,
+    },
+    Removed {
+        path: Vec<u32>,
+        value: ParseValue<'a>,
+    },
+    Changed {
+        path: Vec<u32>,
+        old: ParseValue<'a>,
+        new: ParseValue<'a>,
+    },
+}
+
+impl<'a> Difference<'a> {
+    pub fn path(&self) -> &[u32] {
+        match self {
+            Difference::Added { path, .. }
+            | Difference::Removed { path, .. }
+            | Difference::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// How diff() matches occurrences of the same tag
+///
+/// By default, the n-th occurrence in one message is compared to the
+/// n-th occurrence in the other one. The order of different tags never
+/// matters.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct DiffOptions {
+    /// Match equal occurrences regardless of their position
+    pub ignore_order: bool,
+    /// Like ignore_order but duplicates count only once
+    pub repeated_as_set: bool,
+}
+
+/// Compare two messages field by field
+///
+/// Length-delimited values that parse as messages on both sides are
+/// compared recursively, up to DEFAULT_MAX_DEPTH levels deep. Other
+/// changes are reported with the decoded values. Fails if either
+/// message does not parse completely or nests deeper.
+pub fn diff<'a>(
+    old: &'a [u8],
+    new: &'a [u8],
+    options: DiffOptions,
+) -> ParseResult<Vec<Difference<'a>>> {
+    let mut differences = vec![];
+    diff_messages(
+        &mut vec![],
+        by_tag(old)?,
+        by_tag(new)?,
+        options,
+        &mut differences,
+    )?;
+    Ok(differences)
+}
+
+type Fields<'a> = BTreeMap<u32, Vec<ParseValue<'a>>>;
+
+fn by_tag(data: &[u8]) -> ParseResult<Fields<'_>> {
+    let mut fields: Fields = BTreeMap::new();
+    for_each_field(data, |field| {
+        fields.entry(field.tag).or_default().push(field.value)
+    })?;
+    Ok(fields)
+}
+
+fn diff_messages<'a>(
+    path: &mut Vec<u32>,
+    mut old: Fields<'a>,
+    mut new: Fields<'a>,
+    options: DiffOptions,
+    differences: &mut Vec<Difference<'a>>,
+) -> ParseResult<()> {
+    let mut tags: Vec<u32> = old.keys().chain(new.keys()).cloned().collect();
+    tags.sort_unstable();
+    tags.dedup();
+
+    for tag in tags {
+        let mut old_values = old.remove(&tag).unwrap_or_default();
+        let mut new_values = new.remove(&tag).unwrap_or_default();
+        if options.repeated_as_set {
+            dedup(&mut old_values);
+            dedup(&mut new_values);
+        }
+        if options.ignore_order || options.repeated_as_set {
+            // Drop the occurrences that have an equal counterpart
+            let mut unmatched: BTreeMap<_, usize> = BTreeMap::new();
+            for value in &new_values {
+                *unmatched.entry(key(value)).or_default() += 1;
+            }
+            let mut matched: BTreeMap<_, usize> = BTreeMap::new();
+            old_values.retain(|value| match unmatched.get_mut(&key(value)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    *matched.entry(key(value)).or_default() += 1;
+                    false
+                }
+                _ => true,
+            });
+            new_values.retain(|value| match matched.get_mut(&key(value)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            });
+        }
+
+        path.push(tag);
+        let mut old_values = old_values.into_iter();
+        let mut new_values = new_values.into_iter();
+        loop {
+            match (old_values.next(), new_values.next()) {
+                (Some(old), Some(new)) => diff_values(path, old, new, options, differences)?,
+                (Some(value), None) => differences.push(Difference::Removed {
+                    path: path.clone(),
+                    value,
+                }),
+                (None, Some(value)) => differences.push(Difference::Added {
+                    path: path.clone(),
+                    value,
+                }),
+                (None, None) => break,
+            }
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn diff_values<'a>(
+    path: &mut Vec<u32>,
+    old: ParseValue<'a>,
+    new: ParseValue<'a>,
+    options: DiffOptions,
+    differences: &mut Vec<Difference<'a>>,
+) -> ParseResult<()> {
+    if old == new {
+        return Ok(());
+    }
+    if let (ParseValue::LengthDelimited(old), ParseValue::LengthDelimited(new)) = (&old, &new)
+        && let (Ok(old), Ok(new)) = (by_tag(old), by_tag(new))
+    {
+        if path.len() > DEFAULT_MAX_DEPTH {
+            return Err(ParseError::DepthLimitExceeded);
+        }
+        return diff_messages(path, old, new, options, differences);
+    }
+    differences.push(Difference::Changed {
+        path: path.clone(),
+        old,
+        new,
+    });
+    Ok(())
+}
+
+/// Keeps the first of equal values
+fn dedup(values: &mut Vec<ParseValue>) {
+    let mut seen = BTreeSet::new();
+    values.retain(|value| seen.insert(key(value)));
+}
+
+/// Equal for equal values, to sort and look them up
+fn key<'a>(value: &ParseValue<'a>) -> (WireType, u64, &'a [u8]) {
+    match value {
+        ParseValue::Varint(varint) => (WireType::Varint, varint.value, &[]),
+        ParseValue::Value32(value32) => (WireType::Value32, 0, value32.data),
+        ParseValue::Value64(value64) => (WireType::Value64, 0, value64.data),
+        ParseValue::LengthDelimited(data) => (WireType::LengthDelimited, 0, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::*;
+    use crate::varint::Varint;
+
+    fn varint(value: u64) -> ParseValue<'static> {
+        ParseValue::Varint(Varint { value })
+    }
+
+    #[test]
+    fn equal() {
+        let data = [0x08, 0x01, 0x12, 0x02, b'a', b'b'];
+        let reordered = [0x12, 0x02, b'a', b'b', 0x08, 0x01];
+        assert!(
+            diff(&data, &data, DiffOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            diff(&data, &reordered, DiffOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        // 1: 1, 2: 2 vs. 1: 3, 3: 4
+        let old = [0x08, 0x01, 0x10, 0x02];
+        let new = [0x08, 0x03, 0x18, 0x04];
+        assert_eq!(
+            vec![
+                Difference::Changed {
+                    path: vec![1],
+                    old: varint(1),
+                    new: varint(3)
+                },
+                Difference::Removed {
+                    path: vec![2],
+                    value: varint(2)
+                },
+                Difference::Added {
+                    path: vec![3],
+                    value: varint(4)
+                },
+            ],
+            diff(&old, &new, DiffOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn nested() {
+        // 3: { 1: 150, 2: "ab" } vs. 3: { 1: 151, 2: "ab" }
+        let old = [0x1a, 0x07, 0x08, 0x96, 0x01, 0x12, 0x02, b'a', b'b'];
+        let new = [0x1a, 0x07, 0x08, 0x97, 0x01, 0x12, 0x02, b'a', b'b'];
+        assert_eq!(
+            vec![Difference::Changed {
+                path: vec![3, 1],
+                old: varint(150),
+                new: varint(151)
+            }],
+            diff(&old, &new, DiffOptions::default()).unwrap()
+        );
+        // Strings that are no valid messages are compared as a whole
+        let old = [0x12, 0x03, b'a', b'b', b'c'];
+        let new = [0x12, 0x03, b'a', b'b', b'd'];
+        assert_eq!(
+            vec![Difference::Changed {
+                path: vec![2],
+                old: ParseValue::LengthDelimited(b"abc"),
+                new: ParseValue::LengthDelimited(b"abd")
+            }],
+            diff(&old, &new, DiffOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn repeated() {
+        // 1: [1, 2, 2] vs. 1: [2, 1]
+        let old = [0x08, 0x01, 0x08, 0x02, 0x08, 0x02];
+        let new = [0x08, 0x02, 0x08, 0x01];
+        assert_eq!(3, diff(&old, &new, DiffOptions::default()).unwrap().len());
+
+        let options = DiffOptions {
+            ignore_order: true,
+            ..DiffOptions::default()
+        };
+        assert_eq!(
+            vec![Difference::Removed {
+                path: vec![1],
+                value: varint(2)
+            }],
+            diff(&old, &new, options).unwrap()
+        );
+        // The remaining occurrences are compared in order
+        let reordered_old = [0x08, 0x01, 0x08, 0x03, 0x08, 0x02];
+        let reordered_new = [0x08, 0x02, 0x08, 0x04, 0x08, 0x01];
+        assert_eq!(
+            vec![Difference::Changed {
+                path: vec![1],
+                old: varint(3),
+                new: varint(4)
+            }],
+            diff(&reordered_old, &reordered_new, options).unwrap()
+        );
+
+        let options = DiffOptions {
+            repeated_as_set: true,
+            ..DiffOptions::default()
+        };
+        assert!(diff(&old, &new, options).unwrap().is_empty());
+        assert_eq!(&[1], diff(&old, &[], options).unwrap()[0].path());
+    }
+
+    #[test]
+    fn malformed() {
+        let data = [0x08, 0x01];
+        // Truncated length-delimited field
+        let truncated = [0x08, 0x01, 0x12, 0x05, b'x'];
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            diff(&data, &truncated, DiffOptions::default())
+        );
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            diff(&truncated, &data, DiffOptions::default())
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        // 1: { 1: { ... 2: value } } nested `depth` levels deep
+        let nested = |depth, value| nested_message(1, depth, &[0x10, value]);
+        let (old, new) = (nested(DEFAULT_MAX_DEPTH, 1), nested(DEFAULT_MAX_DEPTH, 2));
+        let differences = diff(&old, &new, DiffOptions::default()).unwrap();
+        assert_eq!(DEFAULT_MAX_DEPTH + 1, differences[0].path().len());
+        let (old, new) = (
+            nested(DEFAULT_MAX_DEPTH + 1, 1),
+            nested(DEFAULT_MAX_DEPTH + 1, 2),
+        );
+        assert_eq!(
+            Err(ParseError::DepthLimitExceeded),
+            diff(&old, &new, DiffOptions::default())
+        );
+    }
+}
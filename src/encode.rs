@@ -0,0 +1,97 @@
+use alloc::vec::Vec;
+
+use crate::field::*;
+use crate::parse::*;
+
+/// Append `value` in its shortest varint encoding
+pub fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Append the key preceding every field value
+pub fn encode_key(tag: u32, wire_type: WireType, out: &mut Vec<u8>) {
+    encode_varint(((tag as u64) << 3) | wire_type as u64, out);
+}
+
+/// Append a complete length-delimited field
+pub fn encode_length_delimited(tag: u32, data: &[u8], out: &mut Vec<u8>) {
+    encode_key(tag, WireType::LengthDelimited, out);
+    encode_varint(data.len() as u64, out);
+    out.extend_from_slice(data);
+}
+
+impl<'a> ParseValue<'a> {
+    /// Append the value without its key, length-prefixed if delimited
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ParseValue::Value32(value32) => out.extend_from_slice(value32.data),
+            ParseValue::Value64(value64) => out.extend_from_slice(value64.data),
+            ParseValue::Varint(varint) => encode_varint(varint.value, out),
+            ParseValue::LengthDelimited(data) => {
+                encode_varint(data.len() as u64, out);
+                out.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    /// Append key and value
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        encode_key(self.tag, self.value.wire_type(), out);
+        self.value.encode(out);
+    }
+}
+
+/// `innermost` wrapped in `depth` messages in field `tag`
+#[cfg(test)]
+pub(crate) fn nested_message(tag: u32, depth: usize, innermost: &[u8]) -> Vec<u8> {
+    // Built back to front to avoid copying
+    let mut data: Vec<u8> = innermost.iter().rev().copied().collect();
+    let mut prefix = Vec::new();
+    for _ in 0..depth {
+        prefix.clear();
+        encode_key(tag, WireType::LengthDelimited, &mut prefix);
+        encode_varint(data.len() as u64, &mut prefix);
+        data.extend(prefix.iter().rev());
+    }
+    data.reverse();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_iter::MessageIter;
+
+    #[test]
+    fn varints() {
+        let mut out = vec![];
+        encode_varint(1, &mut out);
+        encode_varint(300, &mut out);
+        encode_varint(u64::MAX, &mut out);
+        assert_eq!(
+            vec![
+                0x01, 0xac, 0x02, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01
+            ],
+            out
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = [
+            0x08, 0x96, 0x01, 0x12, 0x07, 0x74, 0x65, 0x73, 0x74, 0x69, 0x6e, 0x67, 0x1d, 1, 2, 3,
+            4, 0x21, 1, 2, 3, 4, 5, 6, 7, 8,
+        ];
+        let mut out = vec![];
+        for field in MessageIter::new(&data) {
+            field.encode(&mut out);
+        }
+        assert_eq!(&data[..], &out[..]);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::parse::ParseValue;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Field<'a> {
     pub tag: u32,
     pub value: ParseValue<'a>,
@@ -12,6 +12,12 @@ impl<'a> From<Field<'a>> for ParseValue<'a> {
     }
 }
 
+impl<'a> From<Field<'a>> for (u32, ParseValue<'a>) {
+    fn from(m: Field<'a>) -> Self {
+        (m.tag, m.value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,114 @@
+use alloc::borrow::Cow;
+
+use crate::parse::*;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Element types of packed fixed32 and fixed64 fields
+///
+/// Implemented for the primitive numbers of 4 and 8 bytes, for which
+/// any bytes are a valid value.
+pub trait FixedWidth: Copy + sealed::Sealed {
+    fn from_le_slice(data: &[u8]) -> Self;
+}
+
+macro_rules! fixed_width {
+    ($($target: ty),*) => {
+        $(
+            impl sealed::Sealed for $target {}
+
+            impl FixedWidth for $target {
+                fn from_le_slice(data: &[u8]) -> Self {
+                    <$target>::from_le_bytes(data.try_into().unwrap())
+                }
+            }
+        )*
+    };
+}
+
+fixed_width!(u32, i32, f32, u64, i64, f64);
+
+impl<'a> ParseValue<'a> {
+    /// Packed fixed-width elements without copying, if possible
+    ///
+    /// On little-endian targets, the payload is borrowed if it is
+    /// aligned for `T`, and copied otherwise. Fails with
+    /// `ParseError::Unexpected` if the value is not length-delimited
+    /// and with `ParseError::NotEnoughData` if the payload ends in a
+    /// partial element.
+    pub fn packed_fixed<T: FixedWidth>(&self) -> ParseResult<Cow<'a, [T]>> {
+        let data = match self {
+            ParseValue::LengthDelimited(data) => *data,
+            _ => return Err(ParseError::Unexpected),
+        };
+        let width = size_of::<T>();
+        if !data.len().is_multiple_of(width) {
+            return Err(ParseError::NotEnoughData);
+        }
+        if cfg!(target_endian = "little") && data.as_ptr().cast::<T>().is_aligned() {
+            // SAFETY: the pointer is aligned, the length is a multiple of
+            // the element size, any bytes are a valid T, and the byte
+            // order matches the wire.
+            let slice =
+                unsafe { core::slice::from_raw_parts(data.as_ptr().cast(), data.len() / width) };
+            return Ok(Cow::Borrowed(slice));
+        }
+        Ok(Cow::Owned(
+            data.chunks_exact(width).map(T::from_le_slice).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_or_copied() {
+        #[repr(align(4))]
+        struct Aligned([u8; 16]);
+
+        let floats = [1.5f32, -2.0, 1e9];
+        // Room for an unaligned copy
+        let mut buf = Aligned([0; 16]);
+        let bytes = &mut buf.0;
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(floats) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+
+        let aligned = ParseValue::LengthDelimited(&bytes[..12]);
+        let result = aligned.packed_fixed::<f32>().unwrap();
+        assert_eq!(floats[..], result[..]);
+        if cfg!(target_endian = "little") {
+            assert!(matches!(result, Cow::Borrowed(_)));
+        }
+
+        bytes.copy_within(..12, 1);
+        let unaligned = ParseValue::LengthDelimited(&bytes[1..13]);
+        let result = unaligned.packed_fixed::<f32>().unwrap();
+        assert_eq!(floats[..], result[..]);
+        assert!(matches!(result, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn errors() {
+        let data = [1, 0, 0, 0, 2, 0, 0, 0, 3];
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            ParseValue::LengthDelimited(&data).packed_fixed::<u32>()
+        );
+        assert_eq!(
+            vec![1, 2],
+            ParseValue::LengthDelimited(&data[..8])
+                .packed_fixed::<i32>()
+                .unwrap()
+                .into_owned()
+        );
+        assert_eq!(
+            Err(ParseError::Unexpected),
+            ParseValue::Varint(crate::Varint { value: 1 }).packed_fixed::<u64>()
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use alloc::borrow::ToOwned;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{boxed::Box, format, vec, vec::Vec};
+
+use crate::classify::*;
+use crate::field::*;
+use crate::packed::*;
+use crate::parse::*;
+use crate::schema::*;
+use crate::span::*;
+use crate::visit::*;
+use crate::walk::DEFAULT_MAX_DEPTH;
+
+/// Observations about the fields of one message type
+///
+/// Feed it with add() and turn it into a draft schema with schema().
+/// Length-delimited values that classify() deems messages are merged
+/// into the nested observations of their field, up to
+/// DEFAULT_MAX_DEPTH levels deep.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InferredMessage {
+    /// Number of messages observed
+    pub count: usize,
+    pub fields: BTreeMap<u32, InferredField>,
+}
+
+/// Observations about all occurrences of one tag
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InferredField {
+    /// Total occurrences in all messages
+    pub count: usize,
+    pub wire_types: BTreeSet<WireType>,
+    /// Occurred more than once in a message
+    pub repeated: bool,
+    /// Best guesses for non-empty length-delimited values
+    pub payloads: BTreeMap<PayloadKind, usize>,
+    /// A scalar value had its most significant bit set
+    pub negative: bool,
+    /// Observations of the values that were guessed to be messages
+    pub nested: Option<Box<InferredMessage>>,
+}
+
+impl InferredMessage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Observe one buffer
+    pub fn add(&mut self, data: &[u8]) {
+        self.count += 1;
+        let mut inference = Inference {
+            root: self,
+            path: vec![],
+            counts: vec![BTreeMap::new()],
+        };
+        // Fields up to malformed data are observed all the same
+        let _ = visit(data, &mut inference);
+    }
+
+    /// Draft schema with this message type called `name`
+    ///
+    /// Nested message types are named after the path to them, like
+    /// `{name}_{tag}`, and fields are called `field_{tag}`.
+    pub fn schema(&self, name: &str) -> Schema {
+        let mut schema = Schema::new();
+        self.add_to(&mut schema, name);
+        schema
+    }
+
+    fn add_to(&self, schema: &mut Schema, name: &str) {
+        let mut message = MessageDescriptor::new(name);
+        for (tag, inferred) in &self.fields {
+            let nested_name = format!("{}_{}", name, tag);
+            let field_type = inferred.field_type(&nested_name);
+            if let (FieldType::Message(_), Some(nested)) = (&field_type, &inferred.nested) {
+                nested.add_to(schema, &nested_name);
+            }
+            let field_name = format!("field_{}", tag);
+            message = message.field(if inferred.repeated || inferred.packed().is_some() {
+                FieldDescriptor::repeated(&field_name, *tag, field_type)
+            } else {
+                FieldDescriptor::new(&field_name, *tag, field_type)
+            });
+        }
+        schema.add(message);
+    }
+}
+
+impl InferredField {
+    /// The most frequent guess for length-delimited values
+    ///
+    /// Ties are resolved in declaration order of PayloadKind.
+    pub fn payload(&self) -> Option<PayloadKind> {
+        let mut best: Option<(PayloadKind, usize)> = None;
+        for (kind, count) in &self.payloads {
+            if best.is_none_or(|(_, best_count)| *count > best_count) {
+                best = Some((*kind, *count));
+            }
+        }
+        best.map(|(kind, _)| kind)
+    }
+
+    /// Wire type of the elements if length-delimited values are packed
+    pub fn packed(&self) -> Option<WireType> {
+        match self.payload()? {
+            PayloadKind::PackedVarint => Some(WireType::Varint),
+            PayloadKind::PackedFixed32 => Some(WireType::Value32),
+            PayloadKind::PackedFixed64 => Some(WireType::Value64),
+            _ => None,
+        }
+    }
+
+    /// Most likely type, with `message_name` for nested messages
+    ///
+    /// Conflicting wire types give `FieldType::Bytes`. Scalars with
+    /// the most significant bit set are guessed to be signed.
+    pub fn field_type(&self, message_name: &str) -> FieldType {
+        let mut scalars = self
+            .wire_types
+            .iter()
+            .filter(|wire_type| **wire_type != WireType::LengthDelimited);
+        let scalar = match (scalars.next(), scalars.next()) {
+            (_, Some(_)) => return FieldType::Bytes,
+            (scalar, None) => scalar.copied(),
+        };
+        let length_delimited = self.wire_types.contains(&WireType::LengthDelimited);
+        let wire_type = match (scalar, self.packed()) {
+            (Some(scalar), Some(packed)) if scalar != packed => return FieldType::Bytes,
+            (Some(scalar), _) if length_delimited && self.packed().is_none() => {
+                // Only empty values are compatible with any scalar
+                if self.payloads.is_empty() {
+                    scalar
+                } else {
+                    return FieldType::Bytes;
+                }
+            }
+            (Some(scalar), _) => scalar,
+            (None, Some(packed)) => packed,
+            (None, None) => {
+                return match self.payload() {
+                    // Unless too deep to be observed
+                    Some(PayloadKind::Message) if self.nested.is_some() => {
+                        FieldType::Message(message_name.to_owned())
+                    }
+                    Some(PayloadKind::String) => FieldType::String,
+                    _ => FieldType::Bytes,
+                };
+            }
+        };
+        match (wire_type, self.negative) {
+            (WireType::Value32, false) => FieldType::Fixed32,
+            (WireType::Value32, true) => FieldType::Sfixed32,
+            (WireType::Value64, false) => FieldType::Fixed64,
+            (WireType::Value64, true) => FieldType::Sfixed64,
+            (_, false) => FieldType::Uint64,
+            (_, true) => FieldType::Int64,
+        }
+    }
+}
+
+/// Visitor that adds to the nested observations of the current message
+struct Inference<'i> {
+    root: &'i mut InferredMessage,
+    /// Tags of the entered fields
+    path: Vec<u32>,
+    /// Occurrences of each tag, per entered message
+    counts: Vec<BTreeMap<u32, usize>>,
+}
+
+impl<'i> Inference<'i> {
+    /// Observations of `tag` in the current message
+    fn field(&mut self, tag: u32) -> &mut InferredField {
+        let mut message = &mut *self.root;
+        for tag in &self.path {
+            message = message
+                .fields
+                .get_mut(tag)
+                .and_then(|field| field.nested.as_deref_mut())
+                .expect("Entered messages have been observed");
+        }
+        message.fields.entry(tag).or_default()
+    }
+}
+
+impl<'a, 'i> Visitor<'a> for Inference<'i> {
+    fn visit_field(&mut self, field: &Field<'a>, _span: &FieldSpan) {
+        let count = self.counts[self.path.len()].entry(field.tag).or_insert(0);
+        *count += 1;
+        let repeated = *count > 1;
+        let inferred = self.field(field.tag);
+        inferred.count += 1;
+        inferred.repeated |= repeated;
+        inferred.wire_types.insert(field.value.wire_type());
+        match field.value.clone() {
+            ParseValue::Varint(varint) => inferred.negative |= varint.value >> 63 != 0,
+            ParseValue::Value32(value32) => inferred.negative |= u32::from(value32) >> 31 != 0,
+            ParseValue::Value64(value64) => inferred.negative |= u64::from(value64) >> 63 != 0,
+            ParseValue::LengthDelimited(_) => {}
+        }
+    }
+
+    /// Every length-delimited value is classified here
+    fn enter_message(&mut self, tag: u32, data: &'a [u8]) -> bool {
+        if data.is_empty() {
+            return false;
+        }
+        let depth = self.path.len();
+        let inferred = self.field(tag);
+        let kind = classify(data).best();
+        *inferred.payloads.entry(kind).or_insert(0) += 1;
+        match kind {
+            PayloadKind::Message if depth < DEFAULT_MAX_DEPTH => {
+                inferred.nested.get_or_insert_with(Default::default).count += 1;
+                self.path.push(tag);
+                self.counts.push(BTreeMap::new());
+                return true;
+            }
+            PayloadKind::PackedVarint => {
+                inferred.negative |=
+                    PackedIter::<PackedVarint, u64>::new(data).any(|value| value >> 63 != 0)
+            }
+            PayloadKind::PackedFixed32 => {
+                inferred.negative |= data.chunks_exact(4).any(|chunk| chunk[3] >> 7 != 0)
+            }
+            PayloadKind::PackedFixed64 => {
+                inferred.negative |= data.chunks_exact(8).any(|chunk| chunk[7] >> 7 != 0)
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn leave_message(&mut self, _tag: u32) {
+        self.path.pop();
+        self.counts.pop();
+    }
+}
+
+/// Draft schema from a corpus of messages of the same type
+pub fn infer_schema<'a, I: IntoIterator<Item = &'a [u8]>>(name: &str, messages: I) -> Schema {
+    let mut inferred = InferredMessage::new();
+    for data in messages {
+        inferred.add(data);
+    }
+    inferred.schema(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1: 150, 2: "testing", 3: { 1: -1 }, 4: [3, 270] packed
+    const FIRST: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g', 0x1a, 0x0b, 0x08,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x22, 0x03, 0x03, 0x8e, 0x02,
+    ];
+    // 1: 1, 5: fixed32 7, 5: fixed32 8
+    const SECOND: &[u8] = &[0x08, 0x01, 0x2d, 7, 0, 0, 0, 0x2d, 8, 0, 0, 0];
+
+    #[test]
+    fn observations() {
+        let mut inferred = InferredMessage::new();
+        inferred.add(FIRST);
+        inferred.add(SECOND);
+        assert_eq!(2, inferred.count);
+        assert_eq!(2, inferred.fields[&1].count);
+        assert!(!inferred.fields[&1].repeated);
+        assert!(inferred.fields[&5].repeated);
+        assert_eq!(Some(PayloadKind::String), inferred.fields[&2].payload());
+        assert_eq!(Some(WireType::Varint), inferred.fields[&4].packed());
+        let nested = inferred.fields[&3].nested.as_ref().unwrap();
+        assert_eq!(1, nested.count);
+        assert!(nested.fields[&1].negative);
+    }
+
+    #[test]
+    fn schema() {
+        let schema = infer_schema("Device", [FIRST, SECOND]);
+        assert_eq!(
+            Some(
+                &MessageDescriptor::new("Device")
+                    .field(FieldDescriptor::new("field_1", 1, FieldType::Uint64))
+                    .field(FieldDescriptor::new("field_2", 2, FieldType::String))
+                    .field(FieldDescriptor::new(
+                        "field_3",
+                        3,
+                        FieldType::Message("Device_3".to_owned())
+                    ))
+                    .field(FieldDescriptor::repeated("field_4", 4, FieldType::Uint64))
+                    .field(FieldDescriptor::repeated("field_5", 5, FieldType::Fixed32))
+            ),
+            schema.message("Device")
+        );
+        assert_eq!(
+            Some(FieldType::Int64),
+            schema
+                .message("Device_3")
+                .and_then(|message| message.by_tag(1))
+                .map(|field| field.field_type.clone())
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        // 1: 1, then 1: fixed32 1
+        let schema = infer_schema("Msg", [&[0x08, 0x01][..], &[0x0d, 1, 0, 0, 0]]);
+        assert_eq!(
+            Some(FieldType::Bytes),
+            schema
+                .message("Msg")
+                .and_then(|message| message.by_tag(1))
+                .map(|field| field.field_type.clone())
+        );
+        // Unpacked and packed occurrences
+        let schema = infer_schema("Msg", [&[0x08, 0x01][..], &[0x0a, 0x02, 0x01, 0x02]]);
+        let field = schema.message("Msg").unwrap().by_tag(1).unwrap();
+        assert_eq!(FieldType::Uint64, field.field_type);
+        assert!(field.repeated);
+    }
+}
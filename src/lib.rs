@@ -1,13 +1,100 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 mod parse;
-pub use parse::ParseValue;
+pub use parse::{ParseError, ParseResult, ParseValue, WireType};
+mod bulk;
+pub use bulk::*;
+#[cfg(feature = "bytes")]
+mod bytes_iter;
+#[cfg(feature = "bytes")]
+pub use bytes_iter::*;
+#[cfg(feature = "alloc")]
+mod canonical;
+#[cfg(feature = "alloc")]
+pub use canonical::*;
+#[cfg(feature = "alloc")]
+mod classify;
+#[cfg(feature = "alloc")]
+pub use classify::*;
+mod delimited;
+pub use delimited::*;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "alloc")]
+pub use diff::*;
+#[cfg(feature = "alloc")]
+mod encode;
+#[cfg(feature = "alloc")]
+pub use encode::*;
 mod field;
 pub use field::*;
+#[cfg(feature = "alloc")]
+mod fixed;
+#[cfg(feature = "alloc")]
+pub use fixed::*;
+#[cfg(feature = "alloc")]
+mod infer;
+#[cfg(feature = "alloc")]
+pub use infer::*;
+#[cfg(feature = "mmap")]
+mod mapped;
+#[cfg(feature = "mmap")]
+pub use mapped::*;
+#[cfg(feature = "alloc")]
+mod mask;
+#[cfg(feature = "alloc")]
+pub use mask::*;
 mod message_iter;
 pub use message_iter::*;
+mod map;
+pub use map::*;
+mod oneof;
+pub use oneof::*;
+#[cfg(feature = "alloc")]
+mod owned;
+#[cfg(feature = "alloc")]
+pub use owned::*;
 mod packed;
 pub use packed::*;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+pub use patch::*;
+#[cfg(feature = "alloc")]
+mod schema;
+#[cfg(feature = "alloc")]
+pub use schema::*;
+mod simd;
+mod span;
+pub use span::*;
+#[cfg(feature = "alloc")]
+mod stats;
+#[cfg(feature = "alloc")]
+pub use stats::*;
+#[cfg(feature = "alloc")]
+mod unknown;
+#[cfg(feature = "alloc")]
+pub use unknown::*;
+#[cfg(feature = "alloc")]
+mod validate;
+#[cfg(feature = "alloc")]
+pub use validate::*;
+#[cfg(feature = "alloc")]
+mod visit;
+#[cfg(feature = "alloc")]
+pub use visit::*;
+#[cfg(feature = "alloc")]
+mod walk;
+#[cfg(feature = "alloc")]
+pub use walk::*;
 
 mod value32;
+pub use value32::Value32;
 mod value64;
+pub use value64::Value64;
 mod varint;
+pub use varint::Varint;
 mod zigzag;
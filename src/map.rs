@@ -0,0 +1,169 @@
+use core::convert::From;
+use core::marker::PhantomData;
+
+use crate::message_iter::*;
+use crate::parse::ParseValue;
+
+/// One entry of a `map<K, V>` field: key at tag 1, value at tag 2
+///
+/// Missing keys and values are defaulted like in the reference
+/// implementation.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MapEntry<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<'a, K, V> From<MessageIter<'a>> for MapEntry<K, V>
+where
+    K: Default + From<ParseValue<'a>>,
+    V: Default + From<ParseValue<'a>>,
+{
+    fn from(fields: MessageIter<'a>) -> Self {
+        let mut entry = MapEntry {
+            key: K::default(),
+            value: V::default(),
+        };
+        // Last occurrence wins, like for any other singular field
+        for field in fields {
+            match field.tag {
+                1 => entry.key = From::from(field.value),
+                2 => entry.value = From::from(field.value),
+                _ => {}
+            }
+        }
+        entry
+    }
+}
+
+impl<'a, K, V> From<ParseValue<'a>> for MapEntry<K, V>
+where
+    K: Default + From<ParseValue<'a>>,
+    V: Default + From<ParseValue<'a>>,
+{
+    fn from(value: ParseValue<'a>) -> Self {
+        From::from(MessageIter::from(value))
+    }
+}
+
+impl<K, V> From<MapEntry<K, V>> for (K, V) {
+    fn from(entry: MapEntry<K, V>) -> Self {
+        (entry.key, entry.value)
+    }
+}
+
+/// Returned by MessageIter.map_entries()
+///
+/// Yields `(key, value)` tuples so that it can be collected into a
+/// `HashMap` or `BTreeMap`. Duplicate keys overwrite earlier ones
+/// there, matching the last-key-wins semantics of protobuf.
+///
+/// A single occurrence of the field is one entry, not a whole map, so
+/// `MessageIter.tag()` converts occurrences to `MapEntry` instead.
+#[derive(Clone)]
+pub struct MapIter<'a, K, V> {
+    inner: ByTag<'a, MessageIter<'a>>,
+    entries: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> From<ByTag<'a, MessageIter<'a>>> for MapIter<'a, K, V> {
+    fn from(inner: ByTag<'a, MessageIter<'a>>) -> Self {
+        MapIter {
+            inner,
+            entries: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for MapIter<'a, K, V>
+where
+    K: Default + From<ParseValue<'a>>,
+    V: Default + From<ParseValue<'a>>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|entry| MapEntry::from(entry).into())
+    }
+}
+
+impl<'a> MessageIter<'a> {
+    /// Iterate the entries of a `map<K, V>` field
+    pub fn map_entries<K, V>(self, tag: u32) -> MapIter<'a, K, V> {
+        From::from(self.tag::<MessageIter>(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    /// map<string, uint32> at tag 5: {"a": 1, "b": 2, "a": 3}
+    const MAP: &[u8] = &[
+        0x2a, 0x05, 0x0a, 0x01, b'a', 0x10, 0x01, 0x2a, 0x05, 0x0a, 0x01, b'b', 0x10, 0x02, 0x2a,
+        0x05, 0x0a, 0x01, b'a', 0x10, 0x03,
+    ];
+
+    #[test]
+    fn hash_map() {
+        let map: HashMap<&[u8], u32> = MessageIter::new(MAP).map_entries(5).collect();
+        assert_eq!(2, map.len());
+        assert_eq!(Some(&3), map.get(&b"a"[..]));
+        assert_eq!(Some(&2), map.get(&b"b"[..]));
+    }
+
+    #[test]
+    fn btree_map() {
+        let map: BTreeMap<&[u8], u32> = MessageIter::new(MAP).map_entries(5).collect();
+        assert_eq!(
+            vec![(&b"a"[..], 3), (&b"b"[..], 2)],
+            map.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        // Entry without key, entry without value, empty entry
+        let data = [0x2a, 0x02, 0x10, 0x07, 0x2a, 0x02, 0x08, 0x04, 0x2a, 0x00];
+        let entries: Vec<(u32, u32)> = MessageIter::new(&data).map_entries(5).collect();
+        assert_eq!(vec![(0, 7), (4, 0), (0, 0)], entries);
+    }
+
+    #[test]
+    fn nested_values() {
+        // map<uint32, Msg> at tag 1 of a wrapper, Msg { 1: 150 }
+        let data = [0x0a, 0x07, 0x08, 0x02, 0x12, 0x03, 0x08, 0x96, 0x01];
+        let iter: MapIter<u32, MessageIter> =
+            MessageIter::from(ParseValue::LengthDelimited(&data)).map_entries(1);
+        let (key, mut value) = iter.last().unwrap();
+        assert_eq!(2, key);
+        assert_eq!(Some(150u32), value.next().map(|field| field.value.into()));
+    }
+
+    #[test]
+    fn iterator_map() {
+        // Iterator::map() must not be shadowed
+        let tags: Vec<u32> = MessageIter::new(MAP).map(|field| field.tag).collect();
+        assert_eq!(vec![5, 5, 5], tags);
+    }
+
+    #[test]
+    fn entries_by_tag() {
+        let map: BTreeMap<&[u8], u32> = MessageIter::new(MAP)
+            .tag::<MapEntry<&[u8], u32>>(5)
+            .map(<(_, _)>::from)
+            .collect();
+        assert_eq!(Some(&3), map.get(&b"a"[..]));
+        assert_eq!(2, map.len());
+    }
+
+    #[test]
+    fn entry() {
+        let entry: MapEntry<u32, u32> = MessageIter::new(&[0x0a, 0x04, 0x08, 0x01, 0x10, 0x02])
+            .tag(1)
+            .next()
+            .unwrap();
+        assert_eq!(MapEntry { key: 1, value: 2 }, entry);
+    }
+}
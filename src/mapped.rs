@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::delimited::*;
+use crate::message_iter::*;
+
+/// A memory-mapped file to be decoded without reading it into memory
+///
+/// Iterators borrow from the mapping, so they cannot outlive it.
+pub struct MappedMessages {
+    mmap: Mmap,
+}
+
+impl MappedMessages {
+    /// Map the file at `path` read-only
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped,
+    /// see `memmap2::Mmap::map()`.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: upheld by the caller
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(mmap.into())
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// The fields of the whole file as one message
+    pub fn messages(&self) -> MessageIter<'_> {
+        MessageIter::new(&self.mmap)
+    }
+
+    /// The file as a stream of length-prefixed messages
+    pub fn delimited(&self) -> DelimitedIter<'_> {
+        DelimitedIter::new(&self.mmap)
+    }
+}
+
+impl From<Mmap> for MappedMessages {
+    fn from(mmap: Mmap) -> Self {
+        MappedMessages { mmap }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_file() {
+        let path = std::env::temp_dir().join(format!("protobuf_iter-{}.bin", std::process::id()));
+        std::fs::write(&path, [0x02, 0x08, 0x01, 0x02, 0x08, 0x02]).unwrap();
+        // SAFETY: the file is private to this test
+        let mapped = unsafe { MappedMessages::open(&path) }.unwrap();
+        assert_eq!(6, mapped.as_slice().len());
+        // A stream of messages is no message itself
+        assert_eq!(0, mapped.messages().count());
+        let values: Vec<u32> = mapped
+            .delimited()
+            .flat_map(|message| message.tag::<u32>(1))
+            .collect();
+        assert_eq!(vec![1, 2], values);
+        drop(mapped);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
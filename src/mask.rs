@@ -0,0 +1,567 @@
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::encode::*;
+use crate::field::*;
+use crate::message_iter::*;
+use crate::parse::*;
+use crate::schema::*;
+use crate::span::*;
+use crate::walk::DEFAULT_MAX_DEPTH;
+
+/// Selection of fields by nested tag paths
+///
+/// A selected tag without sub-paths selects the whole field including
+/// everything nested in it.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct FieldMask {
+    children: BTreeMap<u32, FieldMask>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum MaskError {
+    /// A path in an encoded google.protobuf.FieldMask is no UTF-8
+    InvalidPath,
+    UnknownMessage(String),
+    /// The path up to and including the field that was not found
+    UnknownField(String),
+    /// The path continues after a field that is no message
+    NotAMessage(String),
+    /// A path is longer than DEFAULT_MAX_DEPTH
+    DepthLimitExceeded,
+    /// Tags of a path that continues after a repeated or scalar field,
+    /// which merging cannot descend into
+    InvalidSubPath(Vec<u32>),
+}
+
+impl FieldMask {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_paths<P: AsRef<[u32]>>(paths: impl IntoIterator<Item = P>) -> Self {
+        let mut mask = Self::new();
+        for path in paths {
+            mask.add_path(path.as_ref());
+        }
+        mask
+    }
+
+    /// Resolve dotted field names such as `"header.timestamp"`
+    pub fn from_names(schema: &Schema, message: &str, names: &[&str]) -> Result<Self, MaskError> {
+        let mut mask = Self::new();
+        for name in names {
+            mask.add_path(&resolve_path(schema, message, name)?);
+        }
+        Ok(mask)
+    }
+
+    /// Decode a `google.protobuf.FieldMask` message
+    pub fn from_message(schema: &Schema, message: &str, data: &[u8]) -> Result<Self, MaskError> {
+        let mut mask = Self::new();
+        for path in MessageIter::new(data).tag::<&[u8]>(1) {
+            let name = core::str::from_utf8(path).map_err(|_| MaskError::InvalidPath)?;
+            mask.add_path(&resolve_path(schema, message, name)?);
+        }
+        Ok(mask)
+    }
+
+    /// Select a tag path
+    ///
+    /// Projecting and merging recurse once per level of the deepest
+    /// path. Paths from names are limited to DEFAULT_MAX_DEPTH.
+    pub fn add_path(&mut self, path: &[u32]) {
+        let (tag, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        match self.children.get_mut(tag) {
+            // Already selected as a whole
+            Some(child) if child.is_empty() => {}
+            Some(child) if rest.is_empty() => child.children.clear(),
+            Some(child) => child.add_path(rest),
+            None => {
+                let mut child = Self::new();
+                child.add_path(rest);
+                self.children.insert(*tag, child);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// The sub-mask for a selected tag, empty if selected as a whole
+    pub fn get(&self, tag: u32) -> Option<&FieldMask> {
+        self.children.get(&tag)
+    }
+
+    pub fn contains(&self, tag: u32) -> bool {
+        self.children.contains_key(&tag)
+    }
+
+    /// Copy only the selected fields of `data`
+    ///
+    /// Fields selected as a whole are copied verbatim. Nested messages
+    /// with sub-paths are projected recursively and keep their
+    /// presence even if none of their fields are selected.
+    pub fn project(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        self.project_into(data, &mut out);
+        out
+    }
+
+    fn project_into(&self, data: &[u8], out: &mut Vec<u8>) {
+        for spanned in SpannedIter::new(data) {
+            let child = match self.get(spanned.field.tag) {
+                Some(child) => child,
+                None => continue,
+            };
+            match spanned.field.value {
+                _ if child.is_empty() => out.extend_from_slice(&data[spanned.span.field()]),
+                ParseValue::LengthDelimited(nested) => {
+                    out.extend_from_slice(&data[spanned.span.key]);
+                    let projected = child.project(nested);
+                    encode_varint(projected.len() as u64, out);
+                    out.extend_from_slice(&projected);
+                }
+                // Sub-paths of a scalar select nothing
+                _ => {}
+            }
+        }
+    }
+}
+
+/// How FieldMask.merge() treats fields selected as a whole
+///
+/// The defaults follow the reference implementation: repeated fields
+/// are appended to, and nested messages are merged.
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Default)]
+pub struct MergeOptions {
+    /// Replace nested messages instead of merging into them
+    pub replace_message_fields: bool,
+    /// Replace repeated fields instead of appending to them
+    pub replace_repeated_fields: bool,
+}
+
+impl FieldMask {
+    /// Merge the selected fields of `source` into `destination`
+    ///
+    /// Without a schema, fields selected as a whole are always
+    /// replaced. Tags with sub-paths are treated as singular messages
+    /// that are merged recursively.
+    pub fn merge(&self, source: &[u8], destination: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        // Only fails with a schema
+        let _ = self.merge_into(
+            None,
+            source,
+            destination,
+            MergeOptions::default(),
+            &mut vec![],
+            &mut out,
+        );
+        out
+    }
+
+    /// Like merge() but with update semantics according to the field
+    /// types of `message`
+    ///
+    /// Singular scalar fields are replaced. If absent from `source`
+    /// they are cleared, which is the wire format for proto3 defaults.
+    /// Sub-paths of repeated or scalar fields are rejected with
+    /// `MaskError::InvalidSubPath`, like in the reference
+    /// implementation.
+    pub fn merge_with_schema(
+        &self,
+        schema: &Schema,
+        message: &str,
+        source: &[u8],
+        destination: &[u8],
+        options: MergeOptions,
+    ) -> Result<Vec<u8>, MaskError> {
+        let descriptor = schema
+            .message(message)
+            .ok_or_else(|| MaskError::UnknownMessage(message.to_owned()))?;
+        let mut out = vec![];
+        self.merge_into(
+            Some((schema, descriptor)),
+            source,
+            destination,
+            options,
+            &mut vec![],
+            &mut out,
+        )?;
+        Ok(out)
+    }
+
+    fn merge_into(
+        &self,
+        schema: Option<(&Schema, &MessageDescriptor)>,
+        source: &[u8],
+        destination: &[u8],
+        options: MergeOptions,
+        path: &mut Vec<u32>,
+        out: &mut Vec<u8>,
+    ) -> Result<(), MaskError> {
+        // Untouched fields of the destination stay where they are
+        for spanned in SpannedIter::new(destination) {
+            if !self.contains(spanned.field.tag) {
+                out.extend_from_slice(&destination[spanned.span.field()]);
+            }
+        }
+
+        for (&tag, child) in &self.children {
+            let field = schema.and_then(|(_, descriptor)| descriptor.by_tag(tag));
+            let source_fields = || MessageIter::new(source).filter(move |field| field.tag == tag);
+            let destination_fields =
+                || MessageIter::new(destination).filter(move |field| field.tag == tag);
+
+            if !child.is_empty() {
+                path.push(tag);
+                if let Some(field) = field
+                    && (field.repeated || field.message_type().is_none())
+                {
+                    return Err(MaskError::InvalidSubPath(path.clone()));
+                }
+                let is_payload =
+                    |field: &Field| field.value.wire_type() == WireType::LengthDelimited;
+                if field.is_none() {
+                    // Without a schema, only length-delimited occurrences
+                    // can be messages to descend into
+                    destination_fields()
+                        .filter(|field| !is_payload(field))
+                        .for_each(|field| field.encode(out));
+                }
+                if !source_fields().any(|field| is_payload(&field))
+                    && !destination_fields().any(|field| is_payload(&field))
+                {
+                    path.pop();
+                    continue;
+                }
+                let nested_schema = schema.and_then(|(schema, _)| {
+                    let name = field?.message_type()?;
+                    Some((schema, schema.message(name)?))
+                });
+                let mut payload = vec![];
+                child.merge_into(
+                    nested_schema,
+                    &concat_payloads(source_fields()),
+                    &concat_payloads(destination_fields()),
+                    options,
+                    path,
+                    &mut payload,
+                )?;
+                encode_length_delimited(tag, &payload, out);
+                path.pop();
+                continue;
+            }
+
+            match field {
+                Some(field) if field.repeated => {
+                    if !options.replace_repeated_fields {
+                        destination_fields().for_each(|field| field.encode(out));
+                    }
+                    source_fields().for_each(|field| field.encode(out));
+                }
+                Some(field)
+                    if field.message_type().is_some() && !options.replace_message_fields =>
+                {
+                    if source_fields().next().is_some() {
+                        let payload = concat_payloads(destination_fields().chain(source_fields()));
+                        encode_length_delimited(tag, &payload, out);
+                    } else {
+                        destination_fields().for_each(|field| field.encode(out));
+                    }
+                }
+                Some(field) if field.message_type().is_none() => {
+                    // Last one wins anyway
+                    if let Some(field) = source_fields().last() {
+                        field.encode(out);
+                    }
+                }
+                _ => source_fields().for_each(|field| field.encode(out)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merge occurrences of a nested message the way a parser would
+fn concat_payloads<'a>(fields: impl Iterator<Item = Field<'a>>) -> Vec<u8> {
+    let mut payload = vec![];
+    for field in fields {
+        if let ParseValue::LengthDelimited(data) = field.value {
+            payload.extend_from_slice(data);
+        }
+    }
+    payload
+}
+
+/// Translate a dotted name path into tags
+pub(crate) fn resolve_path(
+    schema: &Schema,
+    message: &str,
+    name: &str,
+) -> Result<Vec<u32>, MaskError> {
+    let mut path = vec![];
+    let mut message = Some(message);
+    if name.split('.').count() > DEFAULT_MAX_DEPTH {
+        return Err(MaskError::DepthLimitExceeded);
+    }
+    for (i, part) in name.split('.').enumerate() {
+        let prefix = || name.split('.').take(i + 1).collect::<Vec<_>>().join(".");
+        let message_name = message.ok_or_else(|| MaskError::NotAMessage(prefix()))?;
+        let descriptor = schema
+            .message(message_name)
+            .ok_or_else(|| MaskError::UnknownMessage(message_name.to_owned()))?;
+        let field = descriptor
+            .by_name(part)
+            .ok_or_else(|| MaskError::UnknownField(prefix()))?;
+        path.push(field.tag);
+        message = field.message_type();
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1: 150, 2: { 1: "blob", 2: 7 }, 3: "text", 2: { 2: 8 }
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x08, 0x0a, 0x04, b'b', b'l', b'o', b'b', 0x10, 0x07, 0x1a, 0x04,
+        b't', b'e', b'x', b't', 0x12, 0x02, 0x10, 0x08,
+    ];
+
+    fn schema() -> Schema {
+        Schema::new()
+            .message_type(
+                MessageDescriptor::new("Outer")
+                    .field(FieldDescriptor::new("id", 1, FieldType::Uint32))
+                    .field(FieldDescriptor::repeated(
+                        "inner",
+                        2,
+                        FieldType::Message("Inner".to_owned()),
+                    ))
+                    .field(FieldDescriptor::new("text", 3, FieldType::String)),
+            )
+            .message_type(
+                MessageDescriptor::new("Inner")
+                    .field(FieldDescriptor::new("blob", 1, FieldType::Bytes))
+                    .field(FieldDescriptor::new("count", 2, FieldType::Uint32)),
+            )
+    }
+
+    #[test]
+    fn paths() {
+        let mut mask = FieldMask::from_paths([&[2, 1][..], &[3]]);
+        assert!(mask.contains(2));
+        assert!(!mask.contains(1));
+        assert!(mask.get(2).unwrap().contains(1));
+        // Selecting the whole field supersedes sub-paths
+        mask.add_path(&[2]);
+        assert!(mask.get(2).unwrap().is_empty());
+        mask.add_path(&[2, 2]);
+        assert!(mask.get(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn project() {
+        let mask = FieldMask::from_paths([&[1][..], &[2, 2]]);
+        let projected = mask.project(DATA);
+        assert_eq!(
+            vec![
+                0x08, 0x96, 0x01, 0x12, 0x02, 0x10, 0x07, 0x12, 0x02, 0x10, 0x08
+            ],
+            projected
+        );
+
+        let mask = FieldMask::from_paths([[3]]);
+        assert_eq!(&DATA[13..19], &mask.project(DATA)[..]);
+        assert!(FieldMask::new().project(DATA).is_empty());
+    }
+
+    #[test]
+    fn names() {
+        let schema = schema();
+        let mask = FieldMask::from_names(&schema, "Outer", &["id", "inner.count"]).unwrap();
+        assert_eq!(FieldMask::from_paths([&[1][..], &[2, 2]]), mask);
+        let counts: Vec<u32> = MessageIter::new(&mask.project(DATA))
+            .tag::<MessageIter>(2)
+            .flat_map(|inner| inner.tag(2))
+            .collect();
+        assert_eq!(vec![7, 8], counts);
+
+        assert_eq!(
+            Err(MaskError::UnknownField("inner.size".to_owned())),
+            FieldMask::from_names(&schema, "Outer", &["inner.size"])
+        );
+        assert_eq!(
+            Err(MaskError::NotAMessage("id.x".to_owned())),
+            FieldMask::from_names(&schema, "Outer", &["id.x"])
+        );
+        assert_eq!(
+            Err(MaskError::UnknownMessage("Other".to_owned())),
+            FieldMask::from_names(&schema, "Other", &["id"])
+        );
+    }
+
+    fn merge_schema() -> Schema {
+        Schema::new()
+            .message_type(
+                MessageDescriptor::new("Msg")
+                    .field(FieldDescriptor::new("id", 1, FieldType::Uint32))
+                    .field(FieldDescriptor::repeated("tags", 2, FieldType::Uint32))
+                    .field(FieldDescriptor::new(
+                        "sub",
+                        3,
+                        FieldType::Message("Sub".to_owned()),
+                    ))
+                    .field(FieldDescriptor::new("name", 4, FieldType::String)),
+            )
+            .message_type(
+                MessageDescriptor::new("Sub")
+                    .field(FieldDescriptor::new("a", 1, FieldType::Uint32))
+                    .field(FieldDescriptor::new("b", 2, FieldType::Uint32)),
+            )
+    }
+
+    /// id: 1, tags: [1], sub: { a: 1, b: 1 }, name: "x"
+    const DESTINATION: &[u8] = &[
+        0x08, 0x01, 0x10, 0x01, 0x1a, 0x04, 0x08, 0x01, 0x10, 0x01, 0x22, 0x01, b'x',
+    ];
+    /// id: 2, tags: [2], sub: { b: 2 }
+    const SOURCE: &[u8] = &[0x08, 0x02, 0x10, 0x02, 0x1a, 0x02, 0x10, 0x02];
+
+    fn values(data: &[u8], tag: u32) -> Vec<u32> {
+        MessageIter::new(data).tag(tag).collect()
+    }
+
+    fn sub_values(data: &[u8], tag: u32) -> Vec<u32> {
+        MessageIter::new(data)
+            .tag::<MessageIter>(3)
+            .flat_map(|sub| sub.tag(tag))
+            .collect()
+    }
+
+    #[test]
+    fn merge_with_schema() {
+        let schema = merge_schema();
+        let mask = FieldMask::from_names(&schema, "Msg", &["id", "tags", "sub.b"]).unwrap();
+        let merged = mask
+            .merge_with_schema(&schema, "Msg", SOURCE, DESTINATION, MergeOptions::default())
+            .unwrap();
+        assert_eq!(vec![2], values(&merged, 1));
+        assert_eq!(vec![1, 2], values(&merged, 2));
+        assert_eq!(vec![1], sub_values(&merged, 1));
+        assert_eq!(vec![2], sub_values(&merged, 2));
+        assert_eq!(
+            vec![&b"x"[..]],
+            MessageIter::new(&merged)
+                .tag::<&[u8]>(4)
+                .collect::<Vec<_>>()
+        );
+        // Only one occurrence of the nested message
+        assert_eq!(1, MessageIter::new(&merged).tag::<&[u8]>(3).count());
+    }
+
+    #[test]
+    fn merge_options() {
+        let schema = merge_schema();
+        let mask = FieldMask::from_names(&schema, "Msg", &["tags", "sub", "name"]).unwrap();
+        let merged = mask
+            .merge_with_schema(&schema, "Msg", SOURCE, DESTINATION, MergeOptions::default())
+            .unwrap();
+        assert_eq!(vec![1, 2], values(&merged, 2));
+        assert_eq!(vec![1], sub_values(&merged, 1));
+        assert_eq!(2, sub_values(&merged, 2).pop().unwrap());
+        // Absent from source
+        assert_eq!(0, MessageIter::new(&merged).tag::<&[u8]>(4).count());
+
+        let options = MergeOptions {
+            replace_message_fields: true,
+            replace_repeated_fields: true,
+        };
+        let merged = mask
+            .merge_with_schema(&schema, "Msg", SOURCE, DESTINATION, options)
+            .unwrap();
+        assert_eq!(vec![2], values(&merged, 2));
+        assert!(sub_values(&merged, 1).is_empty());
+        assert_eq!(vec![2], sub_values(&merged, 2));
+    }
+
+    #[test]
+    fn merge_sub_paths() {
+        // Merging into one of several items would corrupt the others
+        let mask = FieldMask::from_names(&schema(), "Outer", &["inner.count"]).unwrap();
+        assert_eq!(
+            Err(MaskError::InvalidSubPath(vec![2])),
+            mask.merge_with_schema(&schema(), "Outer", DATA, DATA, MergeOptions::default())
+        );
+        let schema = merge_schema();
+        let mask = FieldMask::from_paths([[3, 1, 1]]);
+        assert_eq!(
+            Err(MaskError::InvalidSubPath(vec![3, 1])),
+            mask.merge_with_schema(&schema, "Msg", SOURCE, DESTINATION, MergeOptions::default())
+        );
+    }
+
+    #[test]
+    fn merge_without_schema() {
+        let mask = FieldMask::from_paths([&[2][..], &[3, 1]]);
+        let merged = mask.merge(SOURCE, DESTINATION);
+        assert_eq!(vec![1], values(&merged, 1));
+        assert_eq!(vec![2], values(&merged, 2));
+        // sub.a is absent from source
+        assert!(sub_values(&merged, 1).is_empty());
+        assert_eq!(vec![1], sub_values(&merged, 2));
+    }
+
+    #[test]
+    fn merge_into_scalar() {
+        // The destination has 1: 5, which is no message to merge into
+        let mask = FieldMask::from_paths([[1, 1]]);
+        assert_eq!(vec![0x08, 0x05], mask.merge(&[], &[0x08, 0x05]));
+        assert_eq!(vec![0x08, 0x05], mask.merge(&[0x08, 0x06], &[0x08, 0x05]));
+        // Length-delimited occurrences are still merged
+        assert_eq!(
+            vec![0x08, 0x05, 0x0a, 0x02, 0x08, 0x07],
+            mask.merge(&[0x0a, 0x02, 0x08, 0x07], &[0x08, 0x05])
+        );
+    }
+
+    #[test]
+    fn from_message() {
+        let schema = merge_schema();
+        let data = [
+            0x0a, 0x02, b'i', b'd', 0x0a, 0x05, b's', b'u', b'b', b'.', b'a',
+        ];
+        assert_eq!(
+            Ok(FieldMask::from_paths([&[1][..], &[3, 1]])),
+            FieldMask::from_message(&schema, "Msg", &data)
+        );
+        assert_eq!(
+            Err(MaskError::InvalidPath),
+            FieldMask::from_message(&schema, "Msg", &[0x0a, 0x01, 0xff])
+        );
+
+        // Recursive message
+        let schema = Schema::new().message_type(MessageDescriptor::new("Node").field(
+            FieldDescriptor::new("next", 1, FieldType::Message("Node".to_owned())),
+        ));
+        let mut name = vec!["next"; DEFAULT_MAX_DEPTH].join(".");
+        let mut data = vec![];
+        encode_length_delimited(1, name.as_bytes(), &mut data);
+        assert!(FieldMask::from_message(&schema, "Node", &data).is_ok());
+        name.push_str(".next");
+        data.clear();
+        encode_length_delimited(1, name.as_bytes(), &mut data);
+        assert_eq!(
+            Err(MaskError::DepthLimitExceeded),
+            FieldMask::from_message(&schema, "Node", &data)
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use crate::field::*;
 use crate::parse::*;
-use std::convert::From;
-use std::marker::PhantomData;
+use core::convert::From;
+use core::marker::PhantomData;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct MessageIter<'a> {
     data: &'a [u8],
 }
@@ -13,6 +13,39 @@ impl<'a> MessageIter<'a> {
         MessageIter { data }
     }
 
+    /// The data that has not been iterated yet
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Advance past up to `n` fields without decoding their values
+    ///
+    /// Returns the number of fields skipped, which is less than `n`
+    /// if the data ends or cannot be parsed.
+    pub fn skip_fields(&mut self, n: usize) -> usize {
+        for skipped in 0..n {
+            match skip_field(self.data) {
+                Ok((_, rest)) => self.data = rest,
+                Err(_) => return skipped,
+            }
+        }
+        n
+    }
+
+    /// Occurrences of `tag` in the remaining fields, counted without
+    /// decoding values
+    pub fn count_tag(&self, tag: u32) -> usize {
+        let mut data = self.data;
+        let mut count = 0;
+        while let Ok((field_tag, rest)) = skip_field(data) {
+            if field_tag == tag {
+                count += 1;
+            }
+            data = rest;
+        }
+        count
+    }
+
     pub fn tag<T: From<ParseValue<'a>>>(self, tag: u32) -> ByTag<'a, T> {
         ByTag {
             tag,
@@ -22,6 +55,29 @@ impl<'a> MessageIter<'a> {
     }
 }
 
+/// Whether `data` consists of nothing but well-formed fields
+///
+/// Used as a heuristic for length-delimited values in the absence of a
+/// schema. Strings and bytes may happen to parse, too.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_message(data: &[u8]) -> bool {
+    for_each_field(data, |_| {}).is_ok()
+}
+
+/// Calls `f` with every field, failing unless `data` parses completely
+#[cfg(feature = "alloc")]
+pub(crate) fn for_each_field<'a>(
+    mut data: &'a [u8],
+    mut f: impl FnMut(Field<'a>),
+) -> ParseResult<()> {
+    while !data.is_empty() {
+        let (field, rest) = parse_field(data)?;
+        f(field);
+        data = rest;
+    }
+    Ok(())
+}
+
 /// Recursive for LengthDelimited
 impl<'a> From<ParseValue<'a>> for MessageIter<'a> {
     fn from(value: ParseValue<'a>) -> MessageIter<'a> {
@@ -104,8 +160,23 @@ mod tests {
     fn nested_by_tag() {
         let data = [0x1a, 0x03, 0x08, 0x96, 0x01];
         let iter = MessageIter::new(&data);
-        let c = iter.tag::<MessageIter>(3).nth(0).unwrap();
-        let a = c.tag::<u32>(1).nth(0);
+        let c = iter.tag::<MessageIter>(3).next().unwrap();
+        let a = c.tag::<u32>(1).next();
         assert_eq!(Some(150u32), a);
     }
+
+    #[test]
+    fn skip_and_count() {
+        // 1: 1, 2: "ab", 1: 2, 3: fixed32, then garbage
+        let data = [
+            0x08, 0x01, 0x12, 0x02, b'a', b'b', 0x08, 0x02, 0x1d, 0, 0, 0, 0, 0x12,
+        ];
+        let mut iter = MessageIter::new(&data);
+        assert_eq!(2, iter.count_tag(1));
+        assert_eq!(2, iter.skip_fields(2));
+        assert_eq!(1, iter.count_tag(1));
+        assert_eq!(Some(2), iter.next().map(|field| u32::from(field.value)));
+        assert_eq!(1, iter.skip_fields(5));
+        assert_eq!(&[0x12], iter.as_slice());
+    }
 }
@@ -0,0 +1,139 @@
+use core::convert::From;
+
+use crate::field::*;
+use crate::message_iter::*;
+
+/// Set of tags that form a `oneof`
+///
+/// All members are resolved in a single pass. When several members
+/// are present, the last one wins like in the reference implementation.
+#[derive(Debug, Clone, Copy)]
+pub struct Oneof<'t> {
+    tags: &'t [u32],
+}
+
+/// Returned by Oneof.last_checked() when more than one member is set
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct OneofConflict {
+    /// The member that was seen first
+    pub first: u32,
+    /// The other member that was seen afterwards
+    pub second: u32,
+}
+
+impl<'t> Oneof<'t> {
+    pub fn new(tags: &'t [u32]) -> Self {
+        Oneof { tags }
+    }
+
+    pub fn contains(&self, tag: u32) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// The last-set member, converted into `T`
+    ///
+    /// `T` is typically `Field`, `(u32, ParseValue)` or an enum with
+    /// `From<Field>`.
+    pub fn last<'a, T: From<Field<'a>>>(&self, iter: MessageIter<'a>) -> Option<T> {
+        iter.filter(|field| self.contains(field.tag))
+            .last()
+            .map(From::from)
+    }
+
+    /// Like last() but fails if different members are set
+    ///
+    /// Repeated occurrences of the same member are not a conflict.
+    pub fn last_checked<'a, T: From<Field<'a>>>(
+        &self,
+        iter: MessageIter<'a>,
+    ) -> Result<Option<T>, OneofConflict> {
+        let mut last: Option<Field<'a>> = None;
+        for field in iter.filter(|field| self.contains(field.tag)) {
+            match last {
+                Some(Field { tag, .. }) if tag != field.tag => {
+                    return Err(OneofConflict {
+                        first: tag,
+                        second: field.tag,
+                    });
+                }
+                _ => last = Some(field),
+            }
+        }
+        Ok(last.map(From::from))
+    }
+}
+
+impl<'a> MessageIter<'a> {
+    /// Shorthand for Oneof::new(tags).last(self)
+    pub fn oneof<T: From<Field<'a>>>(self, tags: &[u32]) -> Option<T> {
+        Oneof::new(tags).last(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::ParseValue;
+
+    /// 1: 150, 3: "abc", 2: 5, 3: "d"
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x1a, 0x03, b'a', b'b', b'c', 0x10, 0x05, 0x1a, 0x01, b'd',
+    ];
+
+    #[derive(Debug, Eq, PartialEq)]
+    enum Choice<'a> {
+        Number(u32),
+        Text(&'a [u8]),
+    }
+
+    impl<'a> From<Field<'a>> for Choice<'a> {
+        fn from(field: Field<'a>) -> Self {
+            match field.tag {
+                2 => Choice::Number(field.value.into()),
+                _ => Choice::Text(field.value.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn last_member_wins() {
+        let oneof = Oneof::new(&[2, 3]);
+        assert_eq!(
+            Some((3, ParseValue::LengthDelimited(b"d"))),
+            oneof.last(MessageIter::new(DATA))
+        );
+        assert_eq!(
+            Some(Choice::Number(5)),
+            Oneof::new(&[2, 4]).last(MessageIter::new(DATA))
+        );
+        assert_eq!(
+            None,
+            Oneof::new(&[4, 5]).last::<Field>(MessageIter::new(DATA))
+        );
+    }
+
+    #[test]
+    fn enum_members() {
+        assert_eq!(
+            Some(Choice::Text(b"d")),
+            MessageIter::new(DATA).oneof(&[2, 3])
+        );
+    }
+
+    #[test]
+    fn conflicts() {
+        let oneof = Oneof::new(&[2, 3]);
+        assert_eq!(
+            Err(OneofConflict {
+                first: 3,
+                second: 2
+            }),
+            oneof.last_checked::<Field>(MessageIter::new(DATA))
+        );
+        // Repeating one member is fine
+        assert_eq!(
+            Ok(Some(Choice::Text(b"d"))),
+            Oneof::new(&[3]).last_checked(MessageIter::new(DATA))
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::vec::Vec;
+
+use crate::field::*;
+use crate::parse::*;
+use crate::value32::*;
+use crate::value64::*;
+use crate::varint::*;
+
+/// ParseValue that owns its data
+///
+/// Ordering follows the declaration order of the variants, which is
+/// not the order of wire type numbers. Within a variant, fixed-width
+/// values compare by their little-endian bytes, varints by value and
+/// length-delimited data lexicographically.
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedParseValue {
+    Value32([u8; 4]),
+    Value64([u8; 8]),
+    Varint(Varint),
+    LengthDelimited(Vec<u8>),
+}
+
+impl OwnedParseValue {
+    pub fn wire_type(&self) -> WireType {
+        self.as_borrowed().wire_type()
+    }
+
+    /// View as ParseValue for its From conversions
+    pub fn as_borrowed(&self) -> ParseValue<'_> {
+        match self {
+            OwnedParseValue::Value32(data) => ParseValue::Value32(Value32 { data }),
+            OwnedParseValue::Value64(data) => ParseValue::Value64(Value64 { data }),
+            OwnedParseValue::Varint(varint) => ParseValue::Varint(*varint),
+            OwnedParseValue::LengthDelimited(data) => ParseValue::LengthDelimited(data),
+        }
+    }
+}
+
+impl<'a> From<ParseValue<'a>> for OwnedParseValue {
+    fn from(value: ParseValue<'a>) -> Self {
+        match value {
+            ParseValue::Value32(value32) => {
+                OwnedParseValue::Value32(value32.data.try_into().unwrap())
+            }
+            ParseValue::Value64(value64) => {
+                OwnedParseValue::Value64(value64.data.try_into().unwrap())
+            }
+            ParseValue::Varint(varint) => OwnedParseValue::Varint(varint),
+            ParseValue::LengthDelimited(data) => OwnedParseValue::LengthDelimited(data.to_vec()),
+        }
+    }
+}
+
+impl<'a> ParseValue<'a> {
+    /// Copy into an OwnedParseValue
+    ///
+    /// Shadows `ToOwned::to_owned()`. Use clone() for a ParseValue.
+    pub fn to_owned(&self) -> OwnedParseValue {
+        self.clone().into()
+    }
+}
+
+/// Field that owns its data
+#[derive(Debug, Eq, PartialEq, Clone, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedField {
+    pub tag: u32,
+    pub value: OwnedParseValue,
+}
+
+impl OwnedField {
+    pub fn as_borrowed(&self) -> Field<'_> {
+        Field {
+            tag: self.tag,
+            value: self.value.as_borrowed(),
+        }
+    }
+}
+
+impl<'a> From<Field<'a>> for OwnedField {
+    fn from(field: Field<'a>) -> Self {
+        OwnedField {
+            tag: field.tag,
+            value: field.value.into(),
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    /// Copy into an OwnedField
+    ///
+    /// Shadows `ToOwned::to_owned()`. Use clone() for a Field.
+    pub fn to_owned(&self) -> OwnedField {
+        self.clone().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_iter::*;
+    use std::collections::BTreeSet;
+
+    // 1: 150, 2: "ab", 3: fixed32 7, 4: fixed64 8, 1: 150
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x02, b'a', b'b', 0x1d, 7, 0, 0, 0, 0x21, 8, 0, 0, 0, 0, 0, 0, 0,
+        0x08, 0x96, 0x01,
+    ];
+
+    #[test]
+    fn round_trip() {
+        let owned: Vec<OwnedField> = MessageIter::new(DATA).map(OwnedField::from).collect();
+        assert_eq!(
+            MessageIter::new(DATA).collect::<Vec<_>>(),
+            owned
+                .iter()
+                .map(OwnedField::as_borrowed)
+                .collect::<Vec<_>>()
+        );
+        let field = MessageIter::new(DATA).nth(2).unwrap();
+        assert_eq!(owned[2], field.to_owned());
+        assert_eq!(owned[2].value, field.value.to_owned());
+        assert_eq!(7, u32::from(owned[2].value.as_borrowed()));
+    }
+
+    #[test]
+    fn collections() {
+        let set: BTreeSet<OwnedField> = MessageIter::new(DATA).map(OwnedField::from).collect();
+        assert_eq!(4, set.len());
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            set.iter().map(|f| f.tag).collect::<Vec<_>>()
+        );
+        let hashed: std::collections::HashSet<OwnedParseValue> =
+            set.into_iter().map(|field| field.value).collect();
+        assert!(hashed.contains(&OwnedParseValue::Varint(Varint { value: 150 })));
+    }
+
+    #[test]
+    fn ordering() {
+        // Variant order, although fixed32 is wire type 5 and varint 0
+        assert!(OwnedParseValue::Value32([0; 4]) < OwnedParseValue::Varint(Varint { value: 0 }));
+        // Bytes, not numbers
+        assert!(OwnedParseValue::Value32([2, 0, 0, 0]) > OwnedParseValue::Value32([1, 1, 0, 0]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde_test::Token;
+
+        let field = OwnedField {
+            tag: 2,
+            value: OwnedParseValue::LengthDelimited(b"ab".to_vec()),
+        };
+        serde_test::assert_tokens(
+            &field,
+            &[
+                Token::Struct {
+                    name: "OwnedField",
+                    len: 2,
+                },
+                Token::Str("tag"),
+                Token::U32(2),
+                Token::Str("value"),
+                Token::NewtypeVariant {
+                    name: "OwnedParseValue",
+                    variant: "LengthDelimited",
+                },
+                Token::Seq { len: Some(2) },
+                Token::U8(b'a'),
+                Token::U8(b'b'),
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 use crate::parse::*;
+use crate::simd::*;
 use crate::value32::*;
 use crate::value64::*;
 use crate::varint::*;
@@ -9,6 +10,31 @@ pub trait Packed<'a> {
     type Item;
 
     fn parse(data: &'a [u8]) -> ParseResult<(Self::Item, &'a [u8])>;
+
+    /// Bounds on the number of elements in `data`
+    fn size_hint(data: &'a [u8]) -> (usize, Option<usize>) {
+        (0, Some(data.len()))
+    }
+
+    /// The data after the first `n` elements, or nothing if there are
+    /// fewer
+    fn skip(mut data: &'a [u8], n: usize) -> &'a [u8] {
+        for _ in 0..n {
+            match Self::parse(data) {
+                Ok((_, rest)) => data = rest,
+                Err(_) => return &[],
+            }
+        }
+        data
+    }
+}
+
+/// Packed encodings with elements of constant width
+///
+/// Iterators over them are ExactSizeIterator and DoubleEndedIterator.
+/// Trailing bytes that do not make up a whole element are ignored.
+pub trait FixedSize<'a>: Packed<'a> {
+    const WIDTH: usize;
 }
 
 #[derive(Clone, Copy)]
@@ -17,9 +43,14 @@ pub struct PackedVarint;
 impl<'a> Packed<'a> for PackedVarint {
     type Item = Varint;
 
+    #[inline]
     fn parse(data: &'a [u8]) -> ParseResult<(Varint, &'a [u8])> {
         parse_varint(data)
     }
+
+    fn skip(data: &'a [u8], n: usize) -> &'a [u8] {
+        skip_varints(data, n)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -31,6 +62,19 @@ impl<'a> Packed<'a> for PackedValue32 {
     fn parse(data: &'a [u8]) -> ParseResult<(Value32<'a>, &'a [u8])> {
         parse_value32(data)
     }
+
+    fn size_hint(data: &'a [u8]) -> (usize, Option<usize>) {
+        let len = data.len() / 4;
+        (len, Some(len))
+    }
+
+    fn skip(data: &'a [u8], n: usize) -> &'a [u8] {
+        data.get(n.saturating_mul(4)..).unwrap_or_default()
+    }
+}
+
+impl<'a> FixedSize<'a> for PackedValue32 {
+    const WIDTH: usize = 4;
 }
 
 #[derive(Clone, Copy)]
@@ -42,11 +86,24 @@ impl<'a> Packed<'a> for PackedValue64 {
     fn parse(data: &'a [u8]) -> ParseResult<(Value64<'a>, &'a [u8])> {
         parse_value64(data)
     }
+
+    fn size_hint(data: &'a [u8]) -> (usize, Option<usize>) {
+        let len = data.len() / 8;
+        (len, Some(len))
+    }
+
+    fn skip(data: &'a [u8], n: usize) -> &'a [u8] {
+        data.get(n.saturating_mul(8)..).unwrap_or_default()
+    }
+}
+
+impl<'a> FixedSize<'a> for PackedValue64 {
+    const WIDTH: usize = 8;
 }
 
 #[derive(Clone)]
 pub struct PackedIter<'a, P, T> {
-    data: &'a [u8],
+    pub(crate) data: &'a [u8],
     packed: PhantomData<P>,
     item: PhantomData<T>,
 }
@@ -84,7 +141,52 @@ impl<'a, P: Packed<'a>, T: From<<P as Packed<'a>>::Item>> Iterator for PackedIte
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(self.data.len()))
+        P::size_hint(self.data)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.data = P::skip(self.data, n);
+        self.next()
+    }
+}
+
+impl<'a, P: FixedSize<'a>, T: From<<P as Packed<'a>>::Item>> ExactSizeIterator
+    for PackedIter<'a, P, T>
+{
+}
+
+impl<'a, P: FixedSize<'a>, T: From<<P as Packed<'a>>::Item>> DoubleEndedIterator
+    for PackedIter<'a, P, T>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let start = (self.data.len() / P::WIDTH).checked_sub(1)? * P::WIDTH;
+        let (value, _) = P::parse(&self.data[start..]).ok()?;
+        self.data = &self.data[..start];
+        Some(From::from(value))
+    }
+}
+
+impl<'a, T> PackedIter<'a, PackedVarint, T> {
+    /// Number of remaining elements, counted without decoding them
+    ///
+    /// Exact for well-formed data. A trailing unterminated varint is
+    /// not counted.
+    pub fn count_fast(&self) -> usize {
+        count_terminators(self.data)
+    }
+}
+
+impl<'a, P: FixedSize<'a>, T> PackedIter<'a, P, T> {
+    /// Number of remaining elements
+    ///
+    /// Fails with `ParseError::NotEnoughData` if the data ends in a
+    /// partial element.
+    pub fn checked_len(&self) -> ParseResult<usize> {
+        if self.data.len().is_multiple_of(P::WIDTH) {
+            Ok(self.data.len() / P::WIDTH)
+        } else {
+            Err(ParseError::NotEnoughData)
+        }
     }
 }
 
@@ -92,7 +194,7 @@ impl<'a, P: Packed<'a>, T: From<<P as Packed<'a>>::Item>> Iterator for PackedIte
 mod tests {
     use super::*;
 
-    const VARINTS_ENCODED: &'static [u8] = &[0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05];
+    const VARINTS_ENCODED: &[u8] = &[0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05];
 
     #[test]
     fn packed_varints() {
@@ -100,7 +202,7 @@ mod tests {
         assert_eq!(vec![3, 270, 86942], iter.collect::<Vec<u32>>());
     }
 
-    const VALUE32S_ENCODED: &'static [u8] = &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0];
+    const VALUE32S_ENCODED: &[u8] = &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0];
 
     #[test]
     fn packed_value32s() {
@@ -108,7 +210,7 @@ mod tests {
         assert_eq!(vec![1, 2, 3], iter.collect::<Vec<u32>>());
     }
 
-    const VALUE64S_ENCODED: &'static [u8] = &[
+    const VALUE64S_ENCODED: &[u8] = &[
         1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0,
     ];
 
@@ -117,4 +219,54 @@ mod tests {
         let iter: PackedIter<'static, PackedValue64, u32> = PackedIter::new(VALUE64S_ENCODED);
         assert_eq!(vec![1, 2, 3], iter.collect::<Vec<u32>>());
     }
+
+    #[test]
+    fn fixed_size() {
+        let mut iter: PackedIter<'static, PackedValue32, u32> = PackedIter::new(VALUE32S_ENCODED);
+        assert_eq!(3, iter.len());
+        assert_eq!(Ok(3), iter.checked_len());
+        assert_eq!(Some(3), iter.next_back());
+        assert_eq!(Some(1), iter.next());
+        assert_eq!(1, iter.len());
+        assert_eq!(vec![2], iter.rev().collect::<Vec<u32>>());
+
+        let mut iter: PackedIter<'static, PackedValue64, u64> = PackedIter::new(VALUE64S_ENCODED);
+        assert_eq!(Some(3), iter.nth(2));
+        assert_eq!(None, iter.next());
+        let mut iter: PackedIter<'static, PackedValue64, u64> = PackedIter::new(VALUE64S_ENCODED);
+        assert_eq!(None, iter.nth(usize::MAX));
+    }
+
+    #[test]
+    fn partial_element() {
+        let iter: PackedIter<'static, PackedValue32, u32> =
+            PackedIter::new(&VALUE32S_ENCODED[..10]);
+        assert_eq!(Err(ParseError::NotEnoughData), iter.checked_len());
+        assert_eq!(2, iter.len());
+        assert_eq!(vec![2, 1], iter.rev().collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn varint_count_fast() {
+        let iter: PackedIter<'static, PackedVarint, u32> = PackedIter::new(VARINTS_ENCODED);
+        assert_eq!(3, iter.count_fast());
+        let iter: PackedIter<'static, PackedVarint, u32> = PackedIter::new(&VARINTS_ENCODED[..5]);
+        assert_eq!(2, iter.count_fast());
+    }
+
+    #[test]
+    fn varint_nth() {
+        let mut iter: PackedIter<'static, PackedVarint, u32> = PackedIter::new(VARINTS_ENCODED);
+        assert_eq!(Some(86942), iter.nth(2));
+        let mut iter: PackedIter<'static, PackedVarint, u32> = PackedIter::new(VARINTS_ENCODED);
+        assert_eq!(None, iter.nth(3));
+
+        // An overlong varint ends iteration like with next()
+        let mut data = [0x80; 13];
+        data[11] = 0x01;
+        data[12] = 0x05;
+        let mut iter: PackedIter<'_, PackedVarint, u32> = PackedIter::new(&data);
+        assert_eq!(None, iter.clone().next());
+        assert_eq!(None, iter.nth(1));
+    }
 }
@@ -0,0 +1,48 @@
+use rayon::prelude::*;
+
+use crate::message_iter::*;
+use crate::parse::*;
+
+impl<'a> MessageIter<'a> {
+    /// Sub-messages under `tag`, to be decoded in parallel
+    ///
+    /// Field boundaries are scanned sequentially first. Values of other
+    /// wire types are skipped.
+    pub fn par_messages(self, tag: u32) -> impl IndexedParallelIterator<Item = MessageIter<'a>> {
+        let payloads: Vec<&'a [u8]> = self
+            .filter(|field| field.tag == tag)
+            .filter_map(|field| match field.value {
+                ParseValue::LengthDelimited(data) => Some(data),
+                _ => None,
+            })
+            .collect();
+        payloads.into_par_iter().map(MessageIter::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::*;
+
+    #[test]
+    fn sum_in_parallel() {
+        let mut data = vec![];
+        for i in 0..10_000u64 {
+            let mut record = vec![];
+            encode_key(1, WireType::Varint, &mut record);
+            encode_varint(i, &mut record);
+            encode_length_delimited(2, &record, &mut data);
+            // Interleaved with other fields
+            encode_key(3, WireType::Varint, &mut data);
+            encode_varint(i, &mut data);
+        }
+        let sum: u64 = MessageIter::new(&data)
+            .par_messages(2)
+            .map(|record| record.tag::<u64>(1).sum::<u64>())
+            .sum();
+        assert_eq!((0..10_000).sum::<u64>(), sum);
+        assert_eq!(10_000, MessageIter::new(&data).par_messages(2).count());
+        assert_eq!(0, MessageIter::new(&data).par_messages(3).count());
+    }
+}
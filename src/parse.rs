@@ -1,8 +1,9 @@
-use std::convert::From;
-use std::ops::Deref;
+use core::convert::From;
+use core::ops::Deref;
 
 use crate::field::*;
 use crate::packed::*;
+use crate::simd::*;
 use crate::value32::*;
 use crate::value64::*;
 use crate::varint::*;
@@ -15,6 +16,17 @@ pub enum ParseValue<'a> {
     LengthDelimited(&'a [u8]),
 }
 
+/// Encoding of a value on the wire, stored in the lower 3 bits of a key
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub enum WireType {
+    Varint = 0,
+    Value64 = 1,
+    LengthDelimited = 2,
+    StartGroup = 3,
+    EndGroup = 4,
+    Value32 = 5,
+}
+
 impl<'a> Deref for ParseValue<'a> {
     type Target = &'a [u8];
 
@@ -77,6 +89,15 @@ impl<'a> From<ParseValue<'a>> for i64 {
 }
 
 impl<'a> ParseValue<'a> {
+    pub fn wire_type(&self) -> WireType {
+        match self {
+            ParseValue::Value32(_) => WireType::Value32,
+            ParseValue::Value64(_) => WireType::Value64,
+            ParseValue::Varint(_) => WireType::Varint,
+            ParseValue::LengthDelimited(_) => WireType::LengthDelimited,
+        }
+    }
+
     pub fn get_data(self) -> &'a [u8] {
         match self {
             ParseValue::LengthDelimited(data) => data,
@@ -100,12 +121,18 @@ impl<'a> ParseValue<'a> {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+/// New variants may be added in minor releases
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[non_exhaustive]
 pub enum ParseError {
     NotEnoughData,
     DeprecatedType,
     InvalidType,
     Unexpected,
+    /// A varint is longer than 10 bytes
+    MalformedVarint,
+    /// Nested messages exceed the configured depth
+    DepthLimitExceeded,
 }
 
 pub type ParseResult<A> = Result<A, ParseError>;
@@ -135,10 +162,35 @@ fn parse_value64_value<'a>(data: &'a [u8]) -> ParseResult<(ParseValue<'a>, &'a [
 }
 
 /// Used by packed::PackedVarint to avoid the detour over distinguishing between ParseValue members
-pub fn parse_varint(mut data: &[u8]) -> ParseResult<(Varint, &[u8])> {
+///
+/// With at least 16 bytes of input, short varints are decoded by an
+/// unrolled loop and longer ones as a whole with SIMD. Near the end of
+/// the buffer, bytes are read one at a time.
+#[inline]
+pub fn parse_varint(data: &[u8]) -> ParseResult<(Varint, &[u8])> {
+    let chunk = match data.first_chunk::<16>() {
+        Some(chunk) => chunk,
+        None => return parse_varint_slow(data),
+    };
+    // Unrolled for the short varints that dominate real data
+    let mut value = 0;
+    for (i, byte) in chunk[..4].iter().enumerate() {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((Varint { value }, &data[i + 1..]));
+        }
+    }
+    let (value, len) = decode_varint(chunk)?;
+    Ok((Varint { value }, &data[len..]))
+}
+
+fn parse_varint_slow(mut data: &[u8]) -> ParseResult<(Varint, &[u8])> {
     let mut shift = 0;
     let mut value = 0;
     while !data.is_empty() {
+        if shift >= 70 {
+            return Err(ParseError::MalformedVarint);
+        }
         let byte = data[0];
         value |= ((byte & 0x7f) as u64) << shift;
         shift += 7;
@@ -158,6 +210,9 @@ fn parse_length_delimited_value<'a>(data: &'a [u8]) -> ParseResult<(ParseValue<'
     match parse_varint_value(data) {
         Ok((ParseValue::Varint(len), data)) => {
             let len: u64 = From::from(len);
+            if len > data.len() as u64 {
+                return Err(ParseError::NotEnoughData);
+            }
             let len = len as usize;
             Ok((ParseValue::LengthDelimited(&data[0..len]), &data[len..]))
         }
@@ -211,6 +266,37 @@ pub fn parse_field<'a>(data: &'a [u8]) -> ParseResult<(Field<'a>, &'a [u8])> {
     }
 }
 
+/// The data after the next varint, without decoding it
+pub fn skip_varint(data: &[u8]) -> ParseResult<&[u8]> {
+    match data.iter().take(10).position(|byte| byte & 0x80 == 0) {
+        Some(len) => Ok(&data[len + 1..]),
+        None if data.len() >= 10 => Err(ParseError::MalformedVarint),
+        None => Err(ParseError::NotEnoughData),
+    }
+}
+
+/// The tag of the next field and the data after it, without decoding
+/// its value
+pub fn skip_field(data: &[u8]) -> ParseResult<(u32, &[u8])> {
+    let (key, data) = parse_varint(data)?;
+    let tag = (key.value >> 3) as u32;
+    let rest = match key.value & 7 {
+        0 => skip_varint(data)?,
+        1 => data.get(8..).ok_or(ParseError::NotEnoughData)?,
+        2 => {
+            let (len, data) = parse_varint(data)?;
+            usize::try_from(len.value)
+                .ok()
+                .and_then(|len| data.get(len..))
+                .ok_or(ParseError::NotEnoughData)?
+        }
+        3 | 4 => return Err(ParseError::DeprecatedType),
+        5 => data.get(4..).ok_or(ParseError::NotEnoughData)?,
+        _ => return Err(ParseError::InvalidType),
+    };
+    Ok((tag, rest))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -302,6 +388,59 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn varint_paths() {
+        // Each value at every distance from the end of the buffer
+        for value in [0, 1, 300, 1 << 56, u64::MAX] {
+            let mut encoded = vec![];
+            crate::encode::encode_varint(value, &mut encoded);
+            for padding in 0..16 {
+                let mut data = encoded.clone();
+                data.resize(encoded.len() + padding, 0x01);
+                assert_eq!(
+                    Ok((Varint { value }, &data[encoded.len()..])),
+                    parse_varint(&data)
+                );
+                assert_eq!(
+                    Err(ParseError::NotEnoughData),
+                    parse_varint(&data[..encoded.len() - 1])
+                );
+            }
+        }
+        assert_eq!(Err(ParseError::MalformedVarint), parse_varint(&[0x80; 16]));
+    }
+
+    #[test]
+    fn skip() {
+        // 1: 150, 2: "testing", 3: fixed64, 4: fixed32
+        let data = [
+            0x08, 0x96, 0x01, 0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g', 0x19, 0, 0, 0,
+            0, 0, 0, 0, 0, 0x25, 0, 0, 0, 0,
+        ];
+        let mut rest = &data[..];
+        let mut tags = vec![];
+        while !rest.is_empty() {
+            let (tag, next) = skip_field(rest).unwrap();
+            assert_eq!(parse_field(rest).unwrap().1, next);
+            tags.push(tag);
+            rest = next;
+        }
+        assert_eq!(vec![1, 2, 3, 4], tags);
+        assert_eq!(Err(ParseError::NotEnoughData), skip_field(&data[3..10]));
+        assert_eq!(Err(ParseError::NotEnoughData), skip_field(&data[..2]));
+        assert_eq!(Err(ParseError::MalformedVarint), skip_varint(&[0x80; 10]));
+    }
+
+    #[test]
+    fn varint_too_long() {
+        let data = [0xff; 11];
+        assert_eq!(parse_varint(&data), Err(ParseError::MalformedVarint));
+        let mut max = [0xff; 10];
+        max[9] = 0x01;
+        assert_eq!(parse_varint(&max).unwrap().0, Varint { value: u64::MAX });
+    }
+
     #[test]
     fn typed() {
         let data32 = &[0x96, 0, 0, 0];
@@ -332,11 +471,11 @@ mod tests {
         assert_eq!(b"testing", value);
     }
 
-    const PACKED_VARINTS: &'static [u8] = &[0x22, 0x06, 0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05];
+    const PACKED_VARINTS: &[u8] = &[0x22, 0x06, 0x03, 0x8E, 0x02, 0x9E, 0xA7, 0x05];
 
     #[test]
     fn packed_varints() {
-        match parse_field(&PACKED_VARINTS) {
+        match parse_field(PACKED_VARINTS) {
             Ok((field, rest)) => {
                 assert_eq!(field.tag, 4);
                 assert_eq!(
@@ -346,11 +485,17 @@ mod tests {
                 assert_eq!(rest.len(), 0);
             }
             _ => {
-                assert!(false);
+                panic!("Expected packed field");
             }
         }
     }
 
+    #[test]
+    fn truncated_length_delimited() {
+        let data = [0x12, 0x07, 0x74, 0x65, 0x73];
+        assert_eq!(parse_field(&data), Err(ParseError::NotEnoughData));
+    }
+
     #[test]
     fn deprecated_value() {
         let data = [1];
@@ -0,0 +1,201 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::parse::*;
+use crate::span::*;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum PatchError {
+    /// The span does not lie within the buffer
+    OutOfBounds,
+    /// The new encoding would not fit into the old value exactly
+    LengthMismatch { expected: usize, found: usize },
+    /// The field is not encoded as the value to write
+    WireType { expected: WireType, found: WireType },
+}
+
+pub type PatchResult = Result<(), PatchError>;
+
+fn patch_bytes(
+    buf: &mut [u8],
+    spanned: &SpannedField,
+    wire_type: WireType,
+    bytes: &[u8],
+) -> PatchResult {
+    let found = spanned.field.value.wire_type();
+    if found != wire_type {
+        return Err(PatchError::WireType {
+            expected: wire_type,
+            found,
+        });
+    }
+    let target = buf
+        .get_mut(spanned.span.value.clone())
+        .ok_or(PatchError::OutOfBounds)?;
+    if target.len() != bytes.len() {
+        return Err(PatchError::LengthMismatch {
+            expected: target.len(),
+            found: bytes.len(),
+        });
+    }
+    target.copy_from_slice(bytes);
+    Ok(())
+}
+
+/// Overwrite a fixed32, sfixed32 or float value in place
+pub fn patch_value32(buf: &mut [u8], spanned: &SpannedField, value: u32) -> PatchResult {
+    patch_bytes(buf, spanned, WireType::Value32, &value.to_le_bytes())
+}
+
+/// Overwrite a fixed64, sfixed64 or double value in place
+pub fn patch_value64(buf: &mut [u8], spanned: &SpannedField, value: u64) -> PatchResult {
+    patch_bytes(buf, spanned, WireType::Value64, &value.to_le_bytes())
+}
+
+/// Overwrite a varint in place if its new encoding is not longer
+///
+/// A shorter encoding is padded with continuation bytes to fill the
+/// old one, which parsers accept as the same value.
+pub fn patch_varint(buf: &mut [u8], spanned: &SpannedField, mut value: u64) -> PatchResult {
+    let len = spanned.span.value.len();
+    let mut bytes = [0; 10];
+    let mut used = 0;
+    loop {
+        bytes[used] = value as u8 & 0x7f;
+        used += 1;
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+        bytes[used - 1] |= 0x80;
+    }
+    if (used..=bytes.len()).contains(&len) {
+        for byte in &mut bytes[used - 1..len - 1] {
+            *byte |= 0x80;
+        }
+        used = len;
+    }
+    patch_bytes(buf, spanned, WireType::Varint, &bytes[..used])
+}
+
+/// Copy `data` with every field of `tag` replaced
+///
+/// `replacement` is raw wire data, usually one or more complete
+/// fields. It is written where the first field of `tag` was, or
+/// appended if there was none. All other ranges are copied verbatim.
+#[cfg(feature = "alloc")]
+pub fn replace_field(data: &[u8], tag: u32, replacement: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + replacement.len());
+    let mut replaced = false;
+    let mut copied = 0;
+    for spanned in SpannedIter::new(data) {
+        if spanned.field.tag != tag {
+            continue;
+        }
+        let range = spanned.span.field();
+        out.extend_from_slice(&data[copied..range.start]);
+        if !replaced {
+            out.extend_from_slice(replacement);
+            replaced = true;
+        }
+        copied = range.end;
+    }
+    // Includes trailing data that did not parse
+    out.extend_from_slice(&data[copied..]);
+    if !replaced {
+        out.extend_from_slice(replacement);
+    }
+    out
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::message_iter::MessageIter;
+
+    /// 1: 150, 2: fixed64 1, 3: fixed32 2, 1: 3
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x11, 1, 0, 0, 0, 0, 0, 0, 0, 0x1d, 2, 0, 0, 0, 0x08, 0x03,
+    ];
+
+    fn field_of(data: &[u8], tag: u32) -> SpannedField<'_> {
+        SpannedIter::new(data)
+            .find(|spanned| spanned.field.tag == tag)
+            .unwrap()
+    }
+
+    #[test]
+    fn fixed_width() {
+        let mut buf = DATA.to_vec();
+        patch_value64(&mut buf, &field_of(DATA, 2), 0x1122334455667788).unwrap();
+        patch_value32(&mut buf, &field_of(DATA, 3), 7).unwrap();
+        let mut iter = MessageIter::new(&buf);
+        assert_eq!(Some(0x1122334455667788u64), iter.clone().tag(2).next());
+        assert_eq!(Some(7u32), iter.clone().tag(3).next());
+        assert_eq!(Some(150u32), iter.next().map(|field| field.value.into()));
+
+        assert_eq!(
+            Err(PatchError::WireType {
+                expected: WireType::Value64,
+                found: WireType::Value32
+            }),
+            patch_value64(&mut buf, &field_of(DATA, 3), 0)
+        );
+        // Not a fixed32 even though the varint takes 4 bytes
+        let data = [0x08, 0x80, 0x80, 0x80, 0x01];
+        assert_eq!(
+            Err(PatchError::WireType {
+                expected: WireType::Value32,
+                found: WireType::Varint
+            }),
+            patch_value32(&mut data.clone(), &field_of(&data, 1), u32::MAX)
+        );
+        assert_eq!(
+            Err(PatchError::OutOfBounds),
+            patch_value32(&mut buf[..10], &field_of(DATA, 3), 0)
+        );
+    }
+
+    #[test]
+    fn varint() {
+        let mut buf = DATA.to_vec();
+        let spanned = field_of(DATA, 1);
+        patch_varint(&mut buf, &spanned, 300).unwrap();
+        assert_eq!(
+            vec![300u32, 3],
+            MessageIter::new(&buf).tag(1).collect::<Vec<_>>()
+        );
+        // Padded to the old length
+        patch_varint(&mut buf, &spanned, 1).unwrap();
+        assert_eq!(&[0x81, 0x00], &buf[1..3]);
+        assert_eq!(
+            vec![1u32, 3],
+            MessageIter::new(&buf).tag(1).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Err(PatchError::LengthMismatch {
+                expected: 2,
+                found: 3
+            }),
+            patch_varint(&mut buf, &spanned, 1 << 14)
+        );
+        assert_eq!(
+            Err(PatchError::WireType {
+                expected: WireType::Varint,
+                found: WireType::Value32
+            }),
+            patch_varint(&mut buf, &field_of(DATA, 3), 0)
+        );
+    }
+
+    #[test]
+    fn replace() {
+        let out = replace_field(DATA, 1, &[0x08, 0x2a]);
+        assert_eq!(&[0x08, 0x2a], &out[..2]);
+        assert_eq!(&DATA[3..17], &out[2..]);
+
+        let out = replace_field(DATA, 4, &[0x20, 0x01]);
+        assert_eq!(DATA, &out[..DATA.len()]);
+        assert_eq!(&[0x20, 0x01], &out[DATA.len()..]);
+    }
+}
@@ -0,0 +1,252 @@
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::parse::WireType;
+
+/// Type of a field as declared in a .proto file
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum FieldType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+    Enum,
+    /// Name of the message type as registered in the Schema
+    Message(String),
+}
+
+impl FieldType {
+    /// Encoding of a single, non-packed value
+    pub fn wire_type(&self) -> WireType {
+        match self {
+            FieldType::Double | FieldType::Fixed64 | FieldType::Sfixed64 => WireType::Value64,
+            FieldType::Float | FieldType::Fixed32 | FieldType::Sfixed32 => WireType::Value32,
+            FieldType::String | FieldType::Bytes | FieldType::Message(_) => {
+                WireType::LengthDelimited
+            }
+            _ => WireType::Varint,
+        }
+    }
+
+    /// Type name in .proto syntax
+    ///
+    /// Enums are printed as int32 because their names are not known.
+    pub fn proto_name(&self) -> &str {
+        match self {
+            FieldType::Double => "double",
+            FieldType::Float => "float",
+            FieldType::Int32 | FieldType::Enum => "int32",
+            FieldType::Int64 => "int64",
+            FieldType::Uint32 => "uint32",
+            FieldType::Uint64 => "uint64",
+            FieldType::Sint32 => "sint32",
+            FieldType::Sint64 => "sint64",
+            FieldType::Fixed32 => "fixed32",
+            FieldType::Fixed64 => "fixed64",
+            FieldType::Sfixed32 => "sfixed32",
+            FieldType::Sfixed64 => "sfixed64",
+            FieldType::Bool => "bool",
+            FieldType::String => "string",
+            FieldType::Bytes => "bytes",
+            FieldType::Message(name) => name,
+        }
+    }
+
+    /// Scalar numeric types may be packed when repeated
+    pub fn is_packable(&self) -> bool {
+        self.wire_type() != WireType::LengthDelimited
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub tag: u32,
+    pub field_type: FieldType,
+    pub repeated: bool,
+}
+
+impl FieldDescriptor {
+    pub fn new(name: &str, tag: u32, field_type: FieldType) -> Self {
+        FieldDescriptor {
+            name: name.to_owned(),
+            tag,
+            field_type,
+            repeated: false,
+        }
+    }
+
+    pub fn repeated(name: &str, tag: u32, field_type: FieldType) -> Self {
+        FieldDescriptor {
+            repeated: true,
+            ..Self::new(name, tag, field_type)
+        }
+    }
+
+    /// Name of the nested message type, if any
+    pub fn message_type(&self) -> Option<&str> {
+        match &self.field_type {
+            FieldType::Message(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct MessageDescriptor {
+    pub name: String,
+    pub fields: Vec<FieldDescriptor>,
+}
+
+impl MessageDescriptor {
+    pub fn new(name: &str) -> Self {
+        MessageDescriptor {
+            name: name.to_owned(),
+            fields: vec![],
+        }
+    }
+
+    /// Builder-style addition of a field
+    pub fn field(mut self, field: FieldDescriptor) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn by_tag(&self, tag: u32) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|field| field.tag == tag)
+    }
+}
+
+/// Message types by name
+///
+/// Fields refer to nested types by name, which allows for recursive
+/// messages.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Schema {
+    messages: BTreeMap<String, MessageDescriptor>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder-style addition of a message type
+    pub fn message_type(mut self, message: MessageDescriptor) -> Self {
+        self.add(message);
+        self
+    }
+
+    pub fn add(&mut self, message: MessageDescriptor) {
+        self.messages.insert(message.name.clone(), message);
+    }
+
+    pub fn message(&self, name: &str) -> Option<&MessageDescriptor> {
+        self.messages.get(name)
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &MessageDescriptor> {
+        self.messages.values()
+    }
+
+    /// Draft .proto file in proto3 syntax, with messages sorted by name
+    pub fn to_proto(&self) -> String {
+        let mut out = "syntax = \"proto3\";\n".to_owned();
+        for message in self.messages() {
+            writeln!(out, "\nmessage {} {{", message.name).unwrap();
+            for field in &message.fields {
+                let label = if field.repeated { "repeated " } else { "" };
+                writeln!(
+                    out,
+                    "  {}{} {} = {};",
+                    label,
+                    field.field_type.proto_name(),
+                    field.name,
+                    field.tag
+                )
+                .unwrap();
+            }
+            out.push_str("}\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        let schema = Schema::new()
+            .message_type(
+                MessageDescriptor::new("Node")
+                    .field(FieldDescriptor::new("id", 1, FieldType::Uint64))
+                    .field(FieldDescriptor::repeated(
+                        "children",
+                        2,
+                        FieldType::Message("Node".to_owned()),
+                    )),
+            )
+            .message_type(MessageDescriptor::new("Empty"));
+        let node = schema.message("Node").unwrap();
+        assert_eq!(Some(1), node.by_name("id").map(|field| field.tag));
+        assert_eq!(Some("Node"), node.by_tag(2).and_then(|f| f.message_type()));
+        assert!(node.by_tag(3).is_none());
+        assert_eq!(2, schema.messages().count());
+    }
+
+    #[test]
+    fn to_proto() {
+        let schema = Schema::new()
+            .message_type(
+                MessageDescriptor::new("Node")
+                    .field(FieldDescriptor::new("id", 1, FieldType::Sint64))
+                    .field(FieldDescriptor::repeated(
+                        "children",
+                        2,
+                        FieldType::Message("Node".to_owned()),
+                    )),
+            )
+            .message_type(MessageDescriptor::new("Empty"));
+        assert_eq!(
+            "syntax = \"proto3\";\n\
+             \n\
+             message Empty {\n\
+             }\n\
+             \n\
+             message Node {\n  \
+             sint64 id = 1;\n  \
+             repeated Node children = 2;\n\
+             }\n",
+            schema.to_proto()
+        );
+    }
+
+    #[test]
+    fn wire_types() {
+        assert_eq!(WireType::Varint, FieldType::Sint32.wire_type());
+        assert_eq!(WireType::Value64, FieldType::Double.wire_type());
+        assert_eq!(WireType::Value32, FieldType::Sfixed32.wire_type());
+        assert_eq!(WireType::LengthDelimited, FieldType::Bytes.wire_type());
+        assert!(FieldType::Bool.is_packable());
+        assert!(!FieldType::String.is_packable());
+    }
+}
@@ -0,0 +1,322 @@
+//! Varint decoding on whole words instead of single bytes
+//!
+//! SSE2 and NEON are part of the x86_64 and aarch64 baselines, so they
+//! are selected at compile time without runtime detection. Other
+//! targets use the scalar fallback.
+
+use crate::parse::{ParseError, ParseResult, parse_varint};
+
+const CONTINUATION: u64 = 0x8080_8080_8080_8080;
+
+/// Bit i is set if byte i has no continuation bit
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[inline]
+pub(crate) fn terminators(chunk: &[u8; 16]) -> u16 {
+    use core::arch::x86_64::*;
+    // SAFETY: the load reads exactly 16 bytes and has no alignment requirement
+    let continuation = unsafe { _mm_movemask_epi8(_mm_loadu_si128(chunk.as_ptr().cast())) };
+    !(continuation as u16)
+}
+
+/// Bit i is set if byte i has no continuation bit
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+#[inline]
+pub(crate) fn terminators(chunk: &[u8; 16]) -> u16 {
+    use core::arch::aarch64::*;
+    const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+    // SAFETY: both loads read exactly 16 bytes
+    let continuation = unsafe {
+        let bits = vmulq_u8(
+            vshrq_n_u8::<7>(vld1q_u8(chunk.as_ptr())),
+            vld1q_u8(WEIGHTS.as_ptr()),
+        );
+        vaddv_u8(vget_low_u8(bits)) as u16 | (vaddv_u8(vget_high_u8(bits)) as u16) << 8
+    };
+    !continuation
+}
+
+/// Bit i is set if byte i has no continuation bit
+#[cfg(not(any(
+    all(target_arch = "x86_64", target_feature = "sse2"),
+    all(target_arch = "aarch64", target_feature = "neon")
+)))]
+#[inline]
+pub(crate) fn terminators(chunk: &[u8; 16]) -> u16 {
+    scalar_terminators(chunk)
+}
+
+/// Fallback for terminators(), compared with it in the tests
+#[cfg_attr(
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "aarch64", target_feature = "neon")
+    ),
+    allow(dead_code)
+)]
+#[inline]
+fn scalar_terminators(chunk: &[u8; 16]) -> u16 {
+    // Gather the inverted top bits of all bytes into the top byte
+    let gather =
+        |word: u64| ((!word & CONTINUATION) >> 7).wrapping_mul(0x0102_0408_1020_4080) >> 56;
+    let low = u64::from_le_bytes(chunk[..8].try_into().unwrap());
+    let high = u64::from_le_bytes(chunk[8..].try_into().unwrap());
+    (gather(low) | gather(high) << 8) as u16
+}
+
+/// The lower 7 bits of the first `len` bytes, but at most 8
+const PAYLOAD: [u64; 11] = {
+    let mut masks = [0; 11];
+    let mut len = 1;
+    while len <= 10 {
+        let bytes = if len < 8 { len } else { 8 };
+        masks[len] = (u64::MAX >> (64 - 8 * bytes)) & !CONTINUATION;
+        len += 1;
+    }
+    masks
+};
+
+/// Concatenate the 7-bit groups of the first `len` bytes, of which
+/// only 8 fit into `word`
+#[inline]
+pub(crate) fn compact(word: u64, len: usize) -> u64 {
+    let mut x = word & PAYLOAD[len];
+    x = (x & 0x007f_007f_007f_007f) | ((x & 0x7f00_7f00_7f00_7f00) >> 1);
+    x = (x & 0x0000_3fff_0000_3fff) | ((x & 0x3fff_0000_3fff_0000) >> 2);
+    (x & 0x0000_0000_0fff_ffff) | ((x & 0x0fff_ffff_0000_0000) >> 4)
+}
+
+/// Decode a varint that starts a 16-byte window
+///
+/// Returns the value and its encoded length.
+#[inline]
+pub(crate) fn decode_varint(chunk: &[u8; 16]) -> ParseResult<(u64, usize)> {
+    let len = terminators(chunk).trailing_zeros() as usize + 1;
+    if len > 10 {
+        return Err(ParseError::MalformedVarint);
+    }
+    let window = u128::from_le_bytes(*chunk);
+    Ok((combine(window as u64, (window >> 64) as u64, len), len))
+}
+
+/// Decode the varint at `start..end`, which must be no longer than 10
+/// bytes and followed by at least 10 bytes of `data`
+#[inline]
+fn decode_at(data: &[u8], start: usize, end: usize) -> u64 {
+    let word = u64::from_le_bytes(data[start..start + 8].try_into().unwrap());
+    let extra = u16::from_le_bytes(data[start + 8..start + 10].try_into().unwrap());
+    combine(word, extra.into(), end - start)
+}
+
+/// Value of a varint of `len` bytes, the first 8 in `word` and the
+/// others in `extra`
+#[inline]
+fn combine(word: u64, extra: u64, len: usize) -> u64 {
+    let ninth = if len > 8 { (extra & 0x7f) << 56 } else { 0 };
+    // Bits beyond 64 are dropped like in the byte-by-byte path
+    let tenth = if len > 9 { (extra >> 8) << 63 } else { 0 };
+    compact(word, len) | ninth | tenth
+}
+
+/// Whether the mask of a window has a gap of 10 bytes or more, which
+/// makes for a varint longer than 10 bytes
+#[inline]
+fn has_overlong(mask: u16) -> bool {
+    let continuation = !mask;
+    let runs2 = continuation & continuation >> 1;
+    let runs4 = runs2 & runs2 >> 2;
+    let runs8 = runs4 & runs4 >> 4;
+    runs8 & runs2 >> 8 != 0
+}
+
+/// Decode consecutive varints into `out` until either runs out
+///
+/// One mask locates all varints that end within a 16-byte window, and
+/// the next window starts after the last of them. Windows with
+/// malformed varints, the last 31 bytes and the last 15 slots are
+/// handled one varint at a time. Returns the number of values written
+/// and of bytes consumed. Stops before the first varint that does not
+/// parse.
+pub(crate) fn decode_varints<T>(
+    data: &[u8],
+    out: &mut [T],
+    convert: impl Fn(u64) -> T,
+) -> (usize, usize) {
+    let mut pos = 0;
+    let mut count = 0;
+    while let Some(chunk) = data[pos..].first_chunk::<32>() {
+        let mask = terminators(chunk.first_chunk().unwrap());
+        let Some(slots) = out.get_mut(count..count + 16) else {
+            break;
+        };
+        if mask == u16::MAX {
+            // Nothing but 1-byte varints
+            for (slot, byte) in slots.iter_mut().zip(&chunk[..16]) {
+                *slot = convert(*byte as u64);
+            }
+            count += 16;
+            pos += 16;
+            continue;
+        }
+        if has_overlong(mask) {
+            break;
+        }
+        let mut mask = mask as u32;
+        let mut start = 0;
+        for slot in slots {
+            if mask == 0 {
+                break;
+            }
+            let end = mask.trailing_zeros() as usize + 1;
+            *slot = convert(decode_at(chunk, start, end));
+            count += 1;
+            mask &= mask - 1;
+            start = end;
+        }
+        pos += start;
+    }
+    let mut rest = &data[pos..];
+    for slot in &mut out[count..] {
+        match parse_varint(rest) {
+            Ok((varint, next)) => {
+                *slot = convert(varint.value);
+                count += 1;
+                rest = next;
+            }
+            Err(_) => break,
+        }
+    }
+    (count, data.len() - rest.len())
+}
+
+/// Bytes without continuation bit, which is the number of varints in
+/// well-formed data
+pub(crate) fn count_terminators(data: &[u8]) -> usize {
+    let mut chunks = data.chunks_exact(16);
+    let count: usize = chunks
+        .by_ref()
+        .map(|chunk| terminators(chunk.try_into().unwrap()).count_ones() as usize)
+        .sum();
+    count
+        + chunks
+            .remainder()
+            .iter()
+            .filter(|byte| **byte < 0x80)
+            .count()
+}
+
+/// The data after the first `n` varints, or nothing if there are fewer
+///
+/// Varints are counted by their last byte, but like with parse_varint(),
+/// there are none after one that is longer than 10 bytes.
+pub(crate) fn skip_varints(data: &[u8], mut n: usize) -> &[u8] {
+    let mut pos = 0;
+    while n > 0
+        && let Some(chunk) = data[pos..].first_chunk::<16>()
+    {
+        let mut mask = terminators(chunk);
+        if has_overlong(mask) {
+            break;
+        }
+        let count = mask.count_ones() as usize;
+        if count >= n {
+            for _ in 1..n {
+                mask &= mask - 1;
+            }
+            return &data[pos + mask.trailing_zeros() as usize + 1..];
+        }
+        n -= count;
+        // A varint that does not end in this window starts the next one
+        pos += 16 - mask.leading_zeros() as usize;
+    }
+    let mut rest = &data[pos..];
+    for _ in 0..n {
+        match parse_varint(rest) {
+            Ok((_, next)) => rest = next,
+            Err(_) => return &[],
+        }
+    }
+    rest
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::encode::*;
+
+    #[test]
+    fn masks() {
+        let mut chunk = [0x80; 16];
+        chunk[3] = 0x01;
+        chunk[15] = 0x7f;
+        assert_eq!(0b1000_0000_0000_1000, terminators(&chunk));
+        assert_eq!(0b1000_0000_0000_1000, scalar_terminators(&chunk));
+        for byte in 0..=255 {
+            chunk[byte as usize % 16] = byte;
+            assert_eq!(scalar_terminators(&chunk), terminators(&chunk));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut values = vec![0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        values.extend((0..64).map(|shift| 1u64 << shift));
+        values.extend((1..64).map(|shift| (1u64 << shift) - 1));
+        for value in values {
+            let mut chunk = vec![];
+            encode_varint(value, &mut chunk);
+            let len = chunk.len();
+            chunk.resize(16, 0xff);
+            assert_eq!(
+                Ok((value, len)),
+                decode_varint(chunk[..].try_into().unwrap()),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(Err(ParseError::MalformedVarint), decode_varint(&[0xff; 16]));
+    }
+
+    #[test]
+    fn decode_many() {
+        // A run of 1-byte varints, 300, then an overlong varint
+        let mut data: Vec<u8> = (0..40).collect();
+        encode_varint(300, &mut data);
+        data.extend([0x80; 10]);
+        data.extend([0x01; 30]);
+        let mut out = [0u64; 100];
+        assert_eq!((41, 42), decode_varints(&data, &mut out, |value| value));
+        assert_eq!((0..40).collect::<Vec<u64>>(), out[..40]);
+        assert_eq!(300, out[40]);
+        // Fewer slots than varints in a window
+        assert_eq!(
+            (20, 20),
+            decode_varints(&data, &mut out[..20], |value| value)
+        );
+    }
+
+    #[test]
+    fn skip_and_count() {
+        let mut data = vec![];
+        for value in 0..100u64 {
+            encode_varint(value << (value % 50), &mut data);
+        }
+        assert_eq!(100, count_terminators(&data));
+        for n in 0..=100 {
+            let mut rest = &data[..];
+            for _ in 0..n {
+                rest = parse_varint(rest).unwrap().1;
+            }
+            assert_eq!(rest, skip_varints(&data, n));
+        }
+        assert!(skip_varints(&data, 101).is_empty());
+
+        // Overlong, in a window and in the last bytes
+        let mut data = vec![0x01; 10];
+        data.extend([0x80; 11]);
+        data.extend([0x01; 20]);
+        assert_eq!(&data[9..], skip_varints(&data, 9));
+        assert!(skip_varints(&data, 11).is_empty());
+        assert!(skip_varints(&data[5..], 6).is_empty());
+        assert!(skip_varints(&data[10..], 1).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use core::convert::From;
+use core::ops::Range;
+
+use crate::field::*;
+use crate::message_iter::*;
+use crate::parse::*;
+
+/// Byte offsets of a field within the original buffer
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FieldSpan {
+    pub key: Range<usize>,
+    /// Excludes the length prefix of length-delimited values
+    pub value: Range<usize>,
+}
+
+impl FieldSpan {
+    /// Key, length prefix and value
+    pub fn field(&self) -> Range<usize> {
+        self.key.start..self.value.end
+    }
+}
+
+/// Returned by SpannedIter
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SpannedField<'a> {
+    pub field: Field<'a>,
+    pub span: FieldSpan,
+}
+
+impl<'a> SpannedField<'a> {
+    /// Iterate a length-delimited value with offsets that still refer
+    /// to the original buffer
+    pub fn nested(&self) -> SpannedIter<'a> {
+        match self.field.value {
+            ParseValue::LengthDelimited(data) => {
+                SpannedIter::with_offset(data, self.span.value.start)
+            }
+            _ => panic!("Expected buffer to parse"),
+        }
+    }
+}
+
+impl<'a> From<SpannedField<'a>> for Field<'a> {
+    fn from(spanned: SpannedField<'a>) -> Self {
+        spanned.field
+    }
+}
+
+/// Like MessageIter but additionally yields the location of each field
+#[derive(Clone, Default)]
+pub struct SpannedIter<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SpannedIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_offset(data, 0)
+    }
+
+    /// `data` starts at `offset` of the buffer that spans refer to
+    pub fn with_offset(data: &'a [u8], offset: usize) -> Self {
+        SpannedIter { data, offset }
+    }
+
+    /// Position of the next field
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The data that has not been iterated yet
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for SpannedIter<'a> {
+    type Item = SpannedField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (field, rest) = parse_field(self.data).ok()?;
+        let key_len = self.data.len() - parse_varint(self.data).ok()?.1.len();
+        let end = self.offset + self.data.len() - rest.len();
+        let value_start = match field.value {
+            ParseValue::LengthDelimited(data) => end - data.len(),
+            _ => self.offset + key_len,
+        };
+        let span = FieldSpan {
+            key: self.offset..self.offset + key_len,
+            value: value_start..end,
+        };
+        self.data = rest;
+        self.offset = end;
+        Some(SpannedField { field, span })
+    }
+}
+
+impl<'a> MessageIter<'a> {
+    /// Switch to yielding spans, relative to the data not iterated yet
+    pub fn spanned(self) -> SpannedIter<'a> {
+        SpannedIter::new(self.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        // 1: 150, 2: "ab", 5: fixed32
+        let data = [0x08, 0x96, 0x01, 0x12, 0x02, b'a', b'b', 0x2d, 1, 2, 3, 4];
+        let spans: Vec<FieldSpan> = MessageIter::new(&data)
+            .spanned()
+            .map(|spanned| spanned.span)
+            .collect();
+        assert_eq!(
+            vec![
+                FieldSpan {
+                    key: 0..1,
+                    value: 1..3
+                },
+                FieldSpan {
+                    key: 3..4,
+                    value: 5..7
+                },
+                FieldSpan {
+                    key: 7..8,
+                    value: 8..12
+                },
+            ],
+            spans
+        );
+        assert_eq!(3..7, spans[1].field());
+        assert_eq!(b"ab", &data[spans[1].value.clone()]);
+    }
+
+    #[test]
+    fn nested_spans() {
+        // 3: { 1: 150 }, 4: { 2: 1 }
+        let data = [0x1a, 0x03, 0x08, 0x96, 0x01, 0x22, 0x02, 0x10, 0x01];
+        let mut outer = SpannedIter::new(&data);
+        outer.next();
+        let second = outer.next().unwrap();
+        assert_eq!(9, outer.offset());
+        let inner: Vec<SpannedField> = second.nested().collect();
+        assert_eq!(1, inner.len());
+        assert_eq!(2, inner[0].field.tag);
+        assert_eq!(
+            FieldSpan {
+                key: 7..8,
+                value: 8..9
+            },
+            inner[0].span
+        );
+    }
+}
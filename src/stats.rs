@@ -0,0 +1,192 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+use crate::field::*;
+use crate::message_iter::*;
+use crate::parse::*;
+use crate::span::*;
+use crate::visit::*;
+use crate::walk::DEFAULT_MAX_DEPTH;
+
+/// Size and value statistics of all occurrences of one tag path
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct FieldStats {
+    pub count: usize,
+    /// Encoded size including keys and length prefixes
+    pub bytes: usize,
+    /// Smallest varint or fixed-width value, as unsigned integer
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    /// Number of varint values by encoded length, starting at 1 byte
+    pub varint_lengths: [usize; 10],
+}
+
+impl FieldStats {
+    /// Average encoded size of one occurrence
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.count as f64
+        }
+    }
+
+    fn add_value(&mut self, value: u64) {
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+}
+
+/// Size breakdown of a message or a corpus of messages of one type
+///
+/// Length-delimited values that parse as messages are analyzed
+/// recursively, up to DEFAULT_MAX_DEPTH levels deep. Their bytes are
+/// counted for both the nested fields and the enclosing one.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Stats {
+    /// Number of messages added
+    pub messages: usize,
+    /// Size of all messages added
+    pub bytes: usize,
+    /// By tag path
+    pub fields: BTreeMap<Vec<u32>, FieldStats>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        self.messages += 1;
+        self.bytes += data.len();
+        let mut collector = Collector {
+            stats: self,
+            path: vec![],
+        };
+        // Fields up to malformed data are counted all the same
+        let _ = visit(data, &mut collector);
+    }
+
+    /// Fraction of all bytes added that is taken by `path`
+    pub fn share(&self, path: &[u32]) -> f64 {
+        match self.fields.get(path) {
+            Some(stats) if self.bytes > 0 => stats.bytes as f64 / self.bytes as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Visitor that adds every field under its tag path
+struct Collector<'s> {
+    stats: &'s mut Stats,
+    /// Tags of the entered fields
+    path: Vec<u32>,
+}
+
+impl<'a, 's> Visitor<'a> for Collector<'s> {
+    fn visit_field(&mut self, field: &Field<'a>, span: &FieldSpan) {
+        let mut path = self.path.clone();
+        path.push(field.tag);
+        let stats = self.stats.fields.entry(path).or_default();
+        stats.count += 1;
+        stats.bytes += span.field().len();
+        match field.value.clone() {
+            ParseValue::Varint(varint) => {
+                stats.add_value(varint.value);
+                stats.varint_lengths[span.value.len() - 1] += 1;
+            }
+            ParseValue::Value32(value32) => stats.add_value(u32::from(value32).into()),
+            ParseValue::Value64(value64) => stats.add_value(value64.into()),
+            ParseValue::LengthDelimited(_) => {}
+        }
+    }
+
+    fn enter_message(&mut self, tag: u32, data: &'a [u8]) -> bool {
+        let enter = self.path.len() < DEFAULT_MAX_DEPTH && is_message(data);
+        if enter {
+            self.path.push(tag);
+        }
+        enter
+    }
+
+    fn leave_message(&mut self, _tag: u32) {
+        self.path.pop();
+    }
+}
+
+/// One line per tag path with count, bytes, average size, share and
+/// value range
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, stats) in &self.fields {
+            let tags: Vec<String> = path.iter().map(|tag| tag.to_string()).collect();
+            write!(
+                f,
+                "{}: count={} bytes={} avg={:.1} share={:.1}%",
+                tags.join("."),
+                stats.count,
+                stats.bytes,
+                stats.average(),
+                100.0 * self.share(path),
+            )?;
+            if let (Some(min), Some(max)) = (stats.min, stats.max) {
+                write!(f, " range={}..={}", min, max)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1: 150, 2: { 1: 1, 1: 300 }, 3: fixed32 7
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x05, 0x08, 0x01, 0x08, 0xac, 0x02, 0x1d, 7, 0, 0, 0,
+    ];
+
+    #[test]
+    fn breakdown() {
+        let mut stats = Stats::new();
+        stats.add(DATA);
+        stats.add(&[0x08, 0x01]);
+        assert_eq!(2, stats.messages);
+        assert_eq!(17, stats.bytes);
+        assert_eq!(
+            Some(&FieldStats {
+                count: 2,
+                bytes: 5,
+                min: Some(1),
+                max: Some(150),
+                varint_lengths: [1, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            }),
+            stats.fields.get(&vec![1])
+        );
+        assert_eq!(2.5, stats.fields[&vec![1]].average());
+        assert_eq!(7, stats.fields[&vec![2]].bytes);
+        assert_eq!(None, stats.fields[&vec![2]].min);
+        assert_eq!(2, stats.fields[&vec![2, 1]].count);
+        assert_eq!(Some(300), stats.fields[&vec![2, 1]].max);
+        assert_eq!(Some(7), stats.fields[&vec![3]].min);
+        assert_eq!(5.0 / 17.0, stats.share(&[1]));
+        assert_eq!(0.0, stats.share(&[4]));
+    }
+
+    #[test]
+    fn report() {
+        let mut stats = Stats::new();
+        stats.add(DATA);
+        assert_eq!(
+            "1: count=1 bytes=3 avg=3.0 share=20.0% range=150..=150\n\
+             2: count=1 bytes=7 avg=7.0 share=46.7%\n\
+             2.1: count=2 bytes=5 avg=2.5 share=33.3% range=1..=300\n\
+             3: count=1 bytes=5 avg=5.0 share=33.3% range=7..=7\n",
+            stats.to_string()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::vec::Vec;
+
+use crate::field::*;
+use crate::message_iter::*;
+use crate::parse::*;
+
+/// Raw byte ranges of the fields that were not claimed
+///
+/// Adjacent unknown fields are coalesced into one range. Re-encoding
+/// emits them verbatim so that pass-through code does not strip fields
+/// it does not know about.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct UnknownFields<'a> {
+    ranges: Vec<&'a [u8]>,
+}
+
+impl<'a> UnknownFields<'a> {
+    pub fn ranges(&self) -> &[&'a [u8]] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Iterate the unknown fields themselves
+    pub fn fields(&self) -> impl Iterator<Item = Field<'a>> + '_ {
+        self.ranges.iter().flat_map(|range| MessageIter::new(range))
+    }
+
+    /// Append all ranges verbatim
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        for range in &self.ranges {
+            out.extend_from_slice(range);
+        }
+    }
+}
+
+/// Returned by MessageIter.split()
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct SplitFields<'a> {
+    /// Fields with a claimed tag, in order of appearance
+    pub known: Vec<Field<'a>>,
+    pub unknown: UnknownFields<'a>,
+}
+
+impl<'a> SplitFields<'a> {
+    /// Append the known fields followed by the unknown ones
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        for field in &self.known {
+            field.encode(out);
+        }
+        self.unknown.encode(out);
+    }
+}
+
+impl<'a> MessageIter<'a> {
+    /// Separate fields with one of `tags` from the raw rest
+    ///
+    /// Trailing data that does not parse is kept as unknown, too.
+    pub fn split(self, tags: &[u32]) -> SplitFields<'a> {
+        let data = self.as_slice();
+        let mut split = SplitFields::default();
+        // Start of the current run of unknown fields
+        let mut unknown_start = None;
+        let mut offset = 0;
+        while offset < data.len() {
+            let (field, rest) = match parse_field(&data[offset..]) {
+                Ok(result) => result,
+                Err(_) => {
+                    unknown_start.get_or_insert(offset);
+                    break;
+                }
+            };
+            if tags.contains(&field.tag) {
+                if let Some(start) = unknown_start.take() {
+                    split.unknown.ranges.push(&data[start..offset]);
+                }
+                split.known.push(field);
+            } else {
+                unknown_start.get_or_insert(offset);
+            }
+            offset = data.len() - rest.len();
+        }
+        if let Some(start) = unknown_start {
+            split.unknown.ranges.push(&data[start..]);
+        }
+        split
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::varint::Varint;
+
+    /// 1: 150, 2: "ab", 3: 1, 4: 2, 1: 3
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, 0x12, 0x02, b'a', b'b', 0x18, 0x01, 0x20, 0x02, 0x08, 0x03,
+    ];
+
+    #[test]
+    fn split() {
+        let split = MessageIter::new(DATA).split(&[1]);
+        assert_eq!(
+            vec![
+                Field {
+                    tag: 1,
+                    value: ParseValue::Varint(Varint { value: 150 })
+                },
+                Field {
+                    tag: 1,
+                    value: ParseValue::Varint(Varint { value: 3 })
+                },
+            ],
+            split.known
+        );
+        assert_eq!(&[&DATA[3..11]], split.unknown.ranges());
+        assert_eq!(
+            vec![2, 3, 4],
+            split
+                .unknown
+                .fields()
+                .map(|field| field.tag)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn pass_through() {
+        let mut split = MessageIter::new(DATA).split(&[1, 3]);
+        split.known[0].value = ParseValue::Varint(Varint { value: 1 });
+        let mut out = vec![];
+        split.encode(&mut out);
+        assert_eq!(
+            vec![
+                0x08, 0x01, 0x18, 0x01, 0x08, 0x03, 0x12, 0x02, b'a', b'b', 0x20, 0x02
+            ],
+            out
+        );
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let data = [0x08, 0x01, 0x12, 0x05, b'a'];
+        let split = MessageIter::new(&data).split(&[1]);
+        assert_eq!(1, split.known.len());
+        assert_eq!(&[&data[2..]], split.unknown.ranges());
+    }
+}
@@ -0,0 +1,309 @@
+use alloc::{vec, vec::Vec};
+
+use crate::message_iter::*;
+use crate::parse::*;
+use crate::schema::*;
+use crate::walk::*;
+
+/// Largest field number allowed by the language guide
+pub const MAX_TAG: u32 = (1 << 29) - 1;
+
+/// Reserved for the protobuf implementation
+pub const RESERVED_TAGS: core::ops::RangeInclusive<u32> = 19000..=19999;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum ValidationErrorKind {
+    /// The data ends in something that is no field
+    Parse(ParseError),
+    TooDeep,
+    TooManyFields,
+    /// Length of a length-delimited value
+    TooLong(usize),
+    InvalidTag(u64),
+    WireType {
+        expected: WireType,
+        found: WireType,
+    },
+    /// A string field contains no UTF-8
+    InvalidUtf8,
+}
+
+/// Returned by Validator.validate()
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ValidationError {
+    /// Tags of the enclosing fields, including the offending one
+    pub path: Vec<u32>,
+    /// Position in the validated buffer
+    pub offset: usize,
+    pub kind: ValidationErrorKind,
+}
+
+/// Structural checks of untrusted input
+///
+/// Without a schema, length-delimited values that parse as messages are
+/// validated recursively. With a schema, only message fields are, and
+/// wire types and strings are checked, too. Tags that the schema does
+/// not know are treated like without one.
+#[derive(Debug, Clone)]
+pub struct Validator<'s> {
+    max_depth: usize,
+    max_fields: usize,
+    max_length: usize,
+    schema: Option<(&'s Schema, &'s MessageDescriptor)>,
+}
+
+impl<'s> Default for Validator<'s> {
+    fn default() -> Self {
+        Validator {
+            max_depth: 100,
+            max_fields: usize::MAX,
+            max_length: usize::MAX,
+            schema: None,
+        }
+    }
+}
+
+impl<'s> Validator<'s> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Levels of nested messages, 100 by default
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Fields in the whole tree
+    pub fn max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = max_fields;
+        self
+    }
+
+    /// Bytes in any length-delimited value
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn schema(mut self, schema: &'s Schema, message: &'s MessageDescriptor) -> Self {
+        self.schema = Some((schema, message));
+        self
+    }
+
+    pub fn validate(&self, data: &[u8]) -> Result<(), ValidationError> {
+        let mut walker = Walker::new(data).max_depth(self.max_depth);
+        // Message types of the entered fields
+        let mut messages = vec![self.schema.map(|(_, message)| message)];
+        let mut fields = 0;
+        while let Some(event) = walker.next() {
+            let field = match event {
+                WalkEvent::Field(field) => field,
+                WalkEvent::Enter(_) => continue,
+                WalkEvent::Leave(_) => {
+                    messages.pop();
+                    continue;
+                }
+                WalkEvent::Error(e) => {
+                    return Err(ValidationError {
+                        path: walker.path().collect(),
+                        offset: walker.offset(),
+                        kind: ValidationErrorKind::Parse(e),
+                    });
+                }
+            };
+            let key = walker.span().map_or(0..0, |span| span.key.clone());
+            let offset = key.start;
+            let error = |walker: &Walker, kind| {
+                Err(ValidationError {
+                    path: walker.path().chain([field.tag]).collect(),
+                    offset,
+                    kind,
+                })
+            };
+
+            fields += 1;
+            if fields > self.max_fields {
+                return error(&walker, ValidationErrorKind::TooManyFields);
+            }
+            // Unlike field.tag, not truncated to 32 bits
+            let tag = parse_varint(&data[key]).map_or(0, |(key, _)| key.value >> 3);
+            if tag < 1 || tag > MAX_TAG.into() || RESERVED_TAGS.contains(&field.tag) {
+                return error(&walker, ValidationErrorKind::InvalidTag(tag));
+            }
+
+            let message = messages[messages.len() - 1];
+            let descriptor = message.and_then(|message| message.by_tag(field.tag));
+            let found = field.value.wire_type();
+            if let Some(descriptor) = descriptor {
+                let expected = descriptor.field_type.wire_type();
+                let packed = descriptor.repeated
+                    && descriptor.field_type.is_packable()
+                    && found == WireType::LengthDelimited;
+                if found != expected && !packed {
+                    return error(&walker, ValidationErrorKind::WireType { expected, found });
+                }
+            }
+
+            if let ParseValue::LengthDelimited(data) = field.value {
+                if data.len() > self.max_length {
+                    return error(&walker, ValidationErrorKind::TooLong(data.len()));
+                }
+                let nested = match descriptor {
+                    Some(descriptor) => match &descriptor.field_type {
+                        FieldType::Message(name) => {
+                            Some(self.schema.and_then(|(schema, _)| schema.message(name)))
+                        }
+                        FieldType::String if core::str::from_utf8(data).is_err() => {
+                            return error(&walker, ValidationErrorKind::InvalidUtf8);
+                        }
+                        _ => None,
+                    },
+                    None if is_message(data) => Some(None),
+                    None => None,
+                };
+                if let Some(nested) = nested {
+                    if walker.enter().is_err() {
+                        return error(&walker, ValidationErrorKind::TooDeep);
+                    }
+                    messages.push(nested);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::nested_message;
+
+    fn schema() -> Schema {
+        Schema::new().message_type(
+            MessageDescriptor::new("Msg")
+                .field(FieldDescriptor::new("id", 1, FieldType::Uint32))
+                .field(FieldDescriptor::new("name", 2, FieldType::String))
+                .field(FieldDescriptor::new(
+                    "sub",
+                    3,
+                    FieldType::Message("Msg".to_owned()),
+                ))
+                .field(FieldDescriptor::repeated("values", 4, FieldType::Fixed32)),
+        )
+    }
+
+    /// 3: { 3: { 1: 1 } }
+    const NESTED: &[u8] = &[0x1a, 0x04, 0x1a, 0x02, 0x08, 0x01];
+
+    #[test]
+    fn limits() {
+        assert_eq!(Ok(()), Validator::new().validate(NESTED));
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![3, 3],
+                offset: 2,
+                kind: ValidationErrorKind::TooDeep
+            }),
+            Validator::new().max_depth(1).validate(NESTED)
+        );
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![3, 3, 1],
+                offset: 4,
+                kind: ValidationErrorKind::TooManyFields
+            }),
+            Validator::new().max_fields(2).validate(NESTED)
+        );
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![3],
+                offset: 0,
+                kind: ValidationErrorKind::TooLong(4)
+            }),
+            Validator::new().max_length(3).validate(NESTED)
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let data = nested_message(1, 100_000, &[0x08, 0x01]);
+        assert_eq!(
+            Ok(()),
+            Validator::new().max_depth(usize::MAX).validate(&data)
+        );
+        assert_eq!(
+            Some(vec![1; DEFAULT_MAX_DEPTH + 1]),
+            Validator::new().validate(&data).err().map(|e| e.path)
+        );
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(
+            Some(ValidationErrorKind::InvalidTag(0)),
+            Validator::new()
+                .validate(&[0x00, 0x01])
+                .err()
+                .map(|e| e.kind)
+        );
+        // 2^32 + 1, which is 1 in 32 bits
+        assert_eq!(
+            Some(ValidationErrorKind::InvalidTag((1 << 32) + 1)),
+            Validator::new()
+                .validate(&[0x88, 0x80, 0x80, 0x80, 0x80, 0x01, 0x01])
+                .err()
+                .map(|e| e.kind)
+        );
+        // 19000 as varint
+        assert_eq!(
+            Some(ValidationErrorKind::InvalidTag(19000)),
+            Validator::new()
+                .validate(&[0xc0, 0xa3, 0x09, 0x01])
+                .err()
+                .map(|e| e.kind)
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![],
+                offset: 2,
+                kind: ValidationErrorKind::Parse(ParseError::NotEnoughData)
+            }),
+            Validator::new().validate(&[0x08, 0x01, 0x12, 0x05, b'a'])
+        );
+    }
+
+    #[test]
+    fn with_schema() {
+        let schema = schema();
+        let validator = Validator::new().schema(&schema, schema.message("Msg").unwrap());
+        // sub: { name: "ok", values: [1] packed, values: 2 }
+        let valid = [
+            0x1a, 0x0f, 0x12, 0x02, b'o', b'k', 0x22, 0x04, 1, 0, 0, 0, 0x25, 2, 0, 0, 0,
+        ];
+        assert_eq!(Ok(()), validator.validate(&valid));
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![3, 2],
+                offset: 2,
+                kind: ValidationErrorKind::InvalidUtf8
+            }),
+            validator.validate(&[0x1a, 0x03, 0x12, 0x01, 0xff])
+        );
+        assert_eq!(
+            Err(ValidationError {
+                path: vec![1],
+                offset: 0,
+                kind: ValidationErrorKind::WireType {
+                    expected: WireType::Varint,
+                    found: WireType::Value32
+                }
+            }),
+            validator.validate(&[0x0d, 1, 0, 0, 0])
+        );
+    }
+}
@@ -27,3 +27,9 @@ impl<'a> From<Value32<'a>> for i64 {
         i32::from_le_bytes(value32.data.try_into().unwrap()) as i64
     }
 }
+
+impl<'a> From<Value32<'a>> for f32 {
+    fn from(value32: Value32<'a>) -> f32 {
+        f32::from_le_bytes(value32.data.try_into().unwrap())
+    }
+}
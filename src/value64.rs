@@ -27,3 +27,9 @@ impl<'a> From<Value64<'a>> for i64 {
         i64::from_le_bytes(value64.data.try_into().unwrap())
     }
 }
+
+impl<'a> From<Value64<'a>> for f64 {
+    fn from(value64: Value64<'a>) -> f64 {
+        f64::from_le_bytes(value64.data.try_into().unwrap())
+    }
+}
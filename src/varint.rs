@@ -1,7 +1,8 @@
 use crate::zigzag::ZigZag;
 
 /// Encapsulated for From
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Varint {
     pub value: u64,
 }
@@ -0,0 +1,216 @@
+use crate::field::*;
+use crate::parse::*;
+use crate::span::*;
+use crate::walk::*;
+
+/// Callbacks for a whole-tree traversal with visit()
+///
+/// All methods default to doing nothing, so implementations only
+/// override what they need. Length-delimited values are either entered
+/// as a message or passed to visit_bytes().
+pub trait Visitor<'a> {
+    /// Called for every field before the callbacks for its value
+    fn visit_field(&mut self, _field: &Field<'a>, _span: &FieldSpan) {}
+
+    fn visit_varint(&mut self, _tag: u32, _value: u64) {}
+
+    fn visit_fixed32(&mut self, _tag: u32, _value: u32) {}
+
+    fn visit_fixed64(&mut self, _tag: u32, _value: u64) {}
+
+    fn visit_bytes(&mut self, _tag: u32, _data: &'a [u8]) {}
+
+    /// Whether to descend into `data` as a nested message
+    fn enter_message(&mut self, _tag: u32, _data: &'a [u8]) -> bool {
+        false
+    }
+
+    /// Called after the last field of an entered message
+    fn leave_message(&mut self, _tag: u32) {}
+}
+
+/// Drive `visitor` over `data`, up to DEFAULT_MAX_DEPTH levels deep
+///
+/// Fails if `data` or an entered message does not parse completely.
+/// The visitor has seen all fields before the error by then.
+pub fn visit<'a, V: Visitor<'a>>(data: &'a [u8], visitor: &mut V) -> ParseResult<()> {
+    Walker::new(data).visit(visitor)
+}
+
+impl<'a> Walker<'a> {
+    /// Drive `visitor` over the remaining fields
+    pub fn visit<V: Visitor<'a>>(mut self, visitor: &mut V) -> ParseResult<()> {
+        while let Some(event) = self.next() {
+            let field = match event {
+                WalkEvent::Field(field) => field,
+                WalkEvent::Enter(_) => continue,
+                WalkEvent::Leave(tag) => {
+                    visitor.leave_message(tag);
+                    continue;
+                }
+                WalkEvent::Error(error) => return Err(error),
+            };
+            if let Some(span) = self.span() {
+                visitor.visit_field(&field, span);
+            }
+            match field.value {
+                ParseValue::Varint(varint) => visitor.visit_varint(field.tag, varint.value),
+                ParseValue::Value32(value32) => visitor.visit_fixed32(field.tag, value32.into()),
+                ParseValue::Value64(value64) => visitor.visit_fixed64(field.tag, value64.into()),
+                ParseValue::LengthDelimited(data) => {
+                    if visitor.enter_message(field.tag, data) {
+                        self.enter()?;
+                    } else {
+                        visitor.visit_bytes(field.tag, data);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text dump that enters all fields with odd tags
+    #[derive(Default)]
+    struct Dump {
+        indent: usize,
+        lines: Vec<String>,
+    }
+
+    impl Dump {
+        fn line(&mut self, line: String) {
+            self.lines
+                .push(format!("{}{}", "  ".repeat(self.indent), line));
+        }
+    }
+
+    impl<'a> Visitor<'a> for Dump {
+        fn visit_varint(&mut self, tag: u32, value: u64) {
+            self.line(format!("{}: {}", tag, value));
+        }
+
+        fn visit_fixed32(&mut self, tag: u32, value: u32) {
+            self.line(format!("{}: {}f32", tag, value));
+        }
+
+        fn visit_fixed64(&mut self, tag: u32, value: u64) {
+            self.line(format!("{}: {}f64", tag, value));
+        }
+
+        fn visit_bytes(&mut self, tag: u32, data: &'a [u8]) {
+            self.line(format!("{}: {:?}", tag, data));
+        }
+
+        fn enter_message(&mut self, tag: u32, _data: &'a [u8]) -> bool {
+            if tag % 2 == 1 {
+                self.line(format!("{} {{", tag));
+                self.indent += 1;
+                true
+            } else {
+                false
+            }
+        }
+
+        fn leave_message(&mut self, _tag: u32) {
+            self.indent -= 1;
+            self.line("}".to_owned());
+        }
+    }
+
+    #[test]
+    fn dump() {
+        // 1: { 2: "ab", 3: { 4: 5 } }, 5: fixed32 1, 6: fixed64 2
+        let data = [
+            0x0a, 0x08, 0x12, 0x02, b'a', b'b', 0x1a, 0x02, 0x20, 0x05, 0x2d, 1, 0, 0, 0, 0x31, 2,
+            0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut dump = Dump::default();
+        visit(&data, &mut dump).unwrap();
+        assert_eq!(
+            vec![
+                "1 {",
+                "  2: [97, 98]",
+                "  3 {",
+                "    4: 5",
+                "  }",
+                "}",
+                "5: 1f32",
+                "6: 2f64",
+            ],
+            dump.lines
+        );
+    }
+
+    #[test]
+    fn spans() {
+        struct Spans(Vec<(u32, FieldSpan)>);
+        impl<'a> Visitor<'a> for Spans {
+            fn visit_field(&mut self, field: &Field<'a>, span: &FieldSpan) {
+                self.0.push((field.tag, span.clone()));
+            }
+
+            fn enter_message(&mut self, _tag: u32, _data: &'a [u8]) -> bool {
+                true
+            }
+        }
+        // 1: { 2: 3 }
+        let mut spans = Spans(vec![]);
+        visit(&[0x0a, 0x02, 0x10, 0x03], &mut spans).unwrap();
+        assert_eq!(
+            vec![
+                (
+                    1,
+                    FieldSpan {
+                        key: 0..1,
+                        value: 2..4
+                    }
+                ),
+                (
+                    2,
+                    FieldSpan {
+                        key: 2..3,
+                        value: 3..4
+                    }
+                ),
+            ],
+            spans.0
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            visit(&[0x08], &mut Dump::default())
+        );
+        // 1: 1, then a truncated field
+        let mut dump = Dump::default();
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            visit(&[0x08, 0x01, 0x12, 0x05, b'x'], &mut dump)
+        );
+        assert_eq!(vec!["1: 1"], dump.lines);
+        // 1: { 2: truncated }
+        assert_eq!(
+            Err(ParseError::NotEnoughData),
+            visit(&[0x0a, 0x01, 0x10], &mut Dump::default())
+        );
+    }
+
+    #[test]
+    fn depth_limit() {
+        let data = [0x0a, 0x02, 0x0a, 0x00];
+        assert_eq!(
+            Ok(()),
+            Walker::new(&data).max_depth(2).visit(&mut Dump::default())
+        );
+        assert_eq!(
+            Err(ParseError::DepthLimitExceeded),
+            Walker::new(&data).max_depth(1).visit(&mut Dump::default())
+        );
+    }
+}
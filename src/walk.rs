@@ -0,0 +1,281 @@
+use alloc::{vec, vec::Vec};
+
+use crate::field::*;
+use crate::parse::*;
+use crate::span::*;
+
+/// Nesting limit of the reference implementation
+pub const DEFAULT_MAX_DEPTH: usize = 100;
+
+/// Returned by Walker
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum WalkEvent<'a> {
+    /// A field of the current message
+    Field(Field<'a>),
+    /// Walker.enter() was called for the previous field with this tag
+    Enter(u32),
+    /// The nested message of the field with this tag has ended
+    Leave(u32),
+    /// The current message has bytes left that do not parse, after
+    /// which the walk ends where it failed
+    Error(ParseError),
+}
+
+struct Frame<'a> {
+    tag: u32,
+    fields: SpannedIter<'a>,
+}
+
+/// Stack-safe traversal of nested messages
+///
+/// The walker yields the fields of the current message. After a
+/// length-delimited field, the caller decides whether it is a message
+/// to descend into by calling enter(). Nesting is tracked on the heap
+/// and limited to a configurable depth. Unlike MessageIter, malformed
+/// data is reported with `WalkEvent::Error`.
+pub struct Walker<'a> {
+    stack: Vec<Frame<'a>>,
+    /// The last field, if it can be entered
+    enterable: Option<SpannedField<'a>>,
+    entered: Option<u32>,
+    span: Option<FieldSpan>,
+    failed: bool,
+    max_depth: usize,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Walker {
+            stack: vec![Frame {
+                tag: 0,
+                fields: SpannedIter::new(data),
+            }],
+            enterable: None,
+            entered: None,
+            span: None,
+            failed: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Levels of nested messages entered, 0 at the top level
+    pub fn depth(&self) -> usize {
+        self.stack.len() - 1
+    }
+
+    /// Tags of the fields that were entered
+    pub fn path(&self) -> impl Iterator<Item = u32> + '_ {
+        self.stack[1..].iter().map(|frame| frame.tag)
+    }
+
+    /// Location of the last field in the walked buffer
+    pub fn span(&self) -> Option<&FieldSpan> {
+        self.span.as_ref()
+    }
+
+    /// Position of the next field in the current message, or of the
+    /// data that did not parse
+    pub fn offset(&self) -> usize {
+        self.stack[self.stack.len() - 1].fields.offset()
+    }
+
+    /// Descend into the value of the last field
+    ///
+    /// Fails with `ParseError::Unexpected` if the last field was not
+    /// length-delimited, and with `ParseError::DepthLimitExceeded` if
+    /// the maximum depth has been reached.
+    pub fn enter(&mut self) -> ParseResult<()> {
+        let spanned = self.enterable.as_ref().ok_or(ParseError::Unexpected)?;
+        if self.depth() >= self.max_depth {
+            return Err(ParseError::DepthLimitExceeded);
+        }
+        let tag = spanned.field.tag;
+        let fields = spanned.nested();
+        self.enterable = None;
+        self.stack.push(Frame { tag, fields });
+        self.entered = Some(tag);
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for Walker<'a> {
+    type Item = WalkEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.enterable = None;
+        if let Some(tag) = self.entered.take() {
+            return Some(WalkEvent::Enter(tag));
+        }
+        if self.failed {
+            return None;
+        }
+
+        let frame = self.stack.last_mut()?;
+        match frame.fields.next() {
+            Some(spanned) => {
+                self.span = Some(spanned.span.clone());
+                let field = spanned.field.clone();
+                if let ParseValue::LengthDelimited(_) = field.value {
+                    self.enterable = Some(spanned);
+                }
+                Some(WalkEvent::Field(field))
+            }
+            None if !frame.fields.as_slice().is_empty() => {
+                let error = parse_field(frame.fields.as_slice())
+                    .err()
+                    .unwrap_or(ParseError::Unexpected);
+                // Nothing more to walk
+                self.failed = true;
+                Some(WalkEvent::Error(error))
+            }
+            None if self.stack.len() > 1 => {
+                self.stack.pop().map(|frame| WalkEvent::Leave(frame.tag))
+            }
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::*;
+
+    /// 1: 1, 2: { 3: { 4: 4 } }, 5: "x"
+    const DATA: &[u8] = &[
+        0x08, 0x01, 0x12, 0x04, 0x1a, 0x02, 0x20, 0x04, 0x2a, 0x01, b'x',
+    ];
+
+    /// Enter every length-delimited field that parses as a message
+    fn walk(mut walker: Walker) -> Result<Vec<String>, ParseError> {
+        let mut events = vec![];
+        while let Some(event) = walker.next() {
+            events.push(match event {
+                WalkEvent::Field(field) => {
+                    let event = format!("field {} at {}", field.tag, walker.depth());
+                    if matches!(field.value, ParseValue::LengthDelimited(data) if data.first() != Some(&b'x'))
+                    {
+                        walker.enter()?;
+                    }
+                    event
+                }
+                WalkEvent::Enter(tag) => format!("enter {}", tag),
+                WalkEvent::Leave(tag) => format!("leave {}", tag),
+                WalkEvent::Error(error) => format!("error {:?}", error),
+            });
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            Ok(vec![
+                "field 1 at 0".to_owned(),
+                "field 2 at 0".to_owned(),
+                "enter 2".to_owned(),
+                "field 3 at 1".to_owned(),
+                "enter 3".to_owned(),
+                "field 4 at 2".to_owned(),
+                "leave 3".to_owned(),
+                "leave 2".to_owned(),
+                "field 5 at 0".to_owned(),
+            ]),
+            walk(Walker::new(DATA))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        // 1: 1, 2: { 3: truncated }
+        let data = [0x08, 0x01, 0x12, 0x02, 0x1a, 0x05];
+        assert_eq!(
+            Ok(vec![
+                "field 1 at 0".to_owned(),
+                "field 2 at 0".to_owned(),
+                "enter 2".to_owned(),
+                "error NotEnoughData".to_owned(),
+            ]),
+            walk(Walker::new(&data))
+        );
+        let mut walker = Walker::new(&data[..5]);
+        walker.next();
+        assert_eq!(
+            Some(WalkEvent::Error(ParseError::NotEnoughData)),
+            walker.next()
+        );
+        assert_eq!(None, walker.next());
+        assert_eq!(0, walker.depth());
+
+        // Stops where it failed
+        let mut walker = Walker::new(&data);
+        walker.next();
+        walker.next();
+        assert_eq!(
+            Some(&FieldSpan {
+                key: 2..3,
+                value: 4..6
+            }),
+            walker.span()
+        );
+        walker.enter().unwrap();
+        walker.next();
+        assert_eq!(
+            Some(WalkEvent::Error(ParseError::NotEnoughData)),
+            walker.next()
+        );
+        assert_eq!(vec![2], walker.path().collect::<Vec<_>>());
+        assert_eq!(4, walker.offset());
+        assert_eq!(None, walker.next());
+    }
+
+    #[test]
+    fn enter_scalar() {
+        let mut walker = Walker::new(DATA);
+        walker.next();
+        assert_eq!(Err(ParseError::Unexpected), walker.enter());
+    }
+
+    #[test]
+    fn depth_limit() {
+        assert!(walk(Walker::new(DATA).max_depth(2)).is_ok());
+        assert_eq!(
+            Err(ParseError::DepthLimitExceeded),
+            walk(Walker::new(DATA).max_depth(1))
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let data = nested_message(1, 100_000, &[0x08, 0x01]);
+        let mut walker = Walker::new(&data).max_depth(usize::MAX);
+        let mut max_depth = 0;
+        while let Some(event) = walker.next() {
+            if let WalkEvent::Field(Field {
+                value: ParseValue::LengthDelimited(_),
+                ..
+            }) = event
+            {
+                walker.enter().unwrap();
+            }
+            max_depth = max_depth.max(walker.depth());
+        }
+        assert_eq!(100_000, max_depth);
+        assert_eq!(0, walker.depth());
+
+        let mut walker = Walker::new(&data);
+        walker.next();
+        for _ in 0..DEFAULT_MAX_DEPTH {
+            walker.enter().unwrap();
+            walker.next();
+            walker.next();
+        }
+        assert_eq!(vec![1; 100], walker.path().collect::<Vec<_>>());
+        assert_eq!(Err(ParseError::DepthLimitExceeded), walker.enter());
+    }
+}
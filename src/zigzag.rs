@@ -24,21 +24,21 @@ mod tests32 {
     #[test]
     fn u32_to_i32() {
         assert_eq!(0i32, 0u32.zigzag());
-        assert_eq!((-1 as i32), 1u32.zigzag());
+        assert_eq!(-1_i32, 1u32.zigzag());
         assert_eq!(1i32, 2u32.zigzag());
-        assert_eq!((-2 as i32), 3u32.zigzag());
+        assert_eq!(-2_i32, 3u32.zigzag());
         assert_eq!(2147483647i32, 4294967294u32.zigzag());
-        assert_eq!((-2147483648 as i32), 4294967295u32.zigzag());
+        assert_eq!(-2147483648_i32, 4294967295u32.zigzag());
     }
 
     #[test]
     fn i32_to_u32() {
         assert_eq!(0i32.zigzag(), 0u32);
-        assert_eq!((-1 as i32).zigzag(), 1u32);
+        assert_eq!((-1_i32).zigzag(), 1u32);
         assert_eq!(1i32.zigzag(), 2u32);
-        assert_eq!((-2 as i32).zigzag(), 3u32);
+        assert_eq!((-2_i32).zigzag(), 3u32);
         assert_eq!(2147483647i32.zigzag(), 4294967294u32);
-        assert_eq!((-2147483648 as i32).zigzag(), 4294967295u32);
+        assert_eq!((-2147483648_i32).zigzag(), 4294967295u32);
     }
 }
 
@@ -63,20 +63,20 @@ mod tests64 {
     #[test]
     fn u64_to_i64() {
         assert_eq!(0i64, 0u64.zigzag());
-        assert_eq!((-1 as i64), 1u64.zigzag());
+        assert_eq!(-1_i64, 1u64.zigzag());
         assert_eq!(1i64, 2u64.zigzag());
-        assert_eq!((-2 as i64), 3u64.zigzag());
+        assert_eq!(-2_i64, 3u64.zigzag());
         assert_eq!(2147483647i64, 4294967294u64.zigzag());
-        assert_eq!((-2147483648 as i64), 4294967295u64.zigzag());
+        assert_eq!(-2147483648_i64, 4294967295u64.zigzag());
     }
 
     #[test]
     fn i64_to_u64() {
         assert_eq!(0i64.zigzag(), 0u64);
-        assert_eq!((-1 as i64).zigzag(), 1u64);
+        assert_eq!((-1_i64).zigzag(), 1u64);
         assert_eq!(1i64.zigzag(), 2u64);
-        assert_eq!((-2 as i64).zigzag(), 3u64);
+        assert_eq!((-2_i64).zigzag(), 3u64);
         assert_eq!(2147483647i64.zigzag(), 4294967294u64);
-        assert_eq!((-2147483648 as i64).zigzag(), 4294967295u64);
+        assert_eq!((-2147483648_i64).zigzag(), 4294967295u64);
     }
 }